@@ -0,0 +1,326 @@
+use std::str::FromStr;
+
+use clap::Args;
+use thiserror::Error;
+
+use crate::message::Message;
+
+/// Options replaying the copied messages more than once.
+///
+/// Copies are made distinct by rewriting their key or stamping a header -
+/// payloads are always copied unmodified.
+#[derive(Debug, Args)]
+pub struct AmplifyOpts {
+    /// Copy the source this many times, one pass after another.
+    #[clap(long, default_value = "1")]
+    pub repeat: usize,
+
+    /// Write each message this many times as it is copied, as if replayed by
+    /// this many concurrent producers.
+    #[clap(long, default_value = "1")]
+    pub streams: usize,
+
+    /// Rewrite the key of every copied message using a template, making each
+    /// copy distinct.
+    ///
+    /// The template can contain the placeholders "{key}" (the original key,
+    /// or nothing if the message has no key), "{iteration}" (the --repeat
+    /// pass, from 0), "{stream}" (the --streams copy, from 0), and "{copy}"
+    /// (a number unique to each copy of a message). Example:
+    /// "{key}-{copy}".
+    #[clap(long)]
+    pub key_template: Option<KeyTemplate>,
+
+    /// Add a header with this name to every copied message, with the "{copy}"
+    /// number as its value.
+    ///
+    /// An existing header with the same name is replaced, so copies of a
+    /// source that was itself amplified remain distinct.
+    #[clap(long)]
+    pub stamp_header: Option<String>,
+}
+
+impl Default for AmplifyOpts {
+    fn default() -> Self {
+        Self {
+            repeat: 1,
+            streams: 1,
+            key_template: None,
+            stamp_header: None,
+        }
+    }
+}
+
+impl AmplifyOpts {
+    /// The number of messages written for each message in the source.
+    pub fn factor(&self) -> usize {
+        self.repeat * self.streams
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum KeyTemplateError {
+    #[error(
+        "unknown placeholder '{{{}}}' (expected '{{key}}', '{{iteration}}', '{{stream}}' or '{{copy}}')",
+        .0
+    )]
+    UnknownPlaceholder(String),
+
+    #[error("unterminated placeholder (missing '}}')")]
+    Unterminated,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Key,
+    Iteration,
+    Stream,
+    Copy,
+}
+
+/// A template for rewriting message keys.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyTemplate(Vec<Segment>);
+
+impl KeyTemplate {
+    /// Render the key for a copy of a message with the original `key`.
+    fn render(&self, key: Option<&[u8]>, iteration: usize, stream: usize, copy: usize) -> Vec<u8> {
+        let mut out = Vec::new();
+        for s in &self.0 {
+            match s {
+                Segment::Literal(v) => out.extend_from_slice(v.as_bytes()),
+                Segment::Key => out.extend_from_slice(key.unwrap_or_default()),
+                Segment::Iteration => out.extend_from_slice(iteration.to_string().as_bytes()),
+                Segment::Stream => out.extend_from_slice(stream.to_string().as_bytes()),
+                Segment::Copy => out.extend_from_slice(copy.to_string().as_bytes()),
+            }
+        }
+        out
+    }
+}
+
+impl FromStr for KeyTemplate {
+    type Err = KeyTemplateError;
+
+    fn from_str(mut s: &str) -> Result<Self, Self::Err> {
+        let mut segments = Vec::new();
+
+        while let Some(start) = s.find('{') {
+            if start > 0 {
+                segments.push(Segment::Literal(s[..start].to_string()));
+            }
+
+            let (name, rest) = s[start + 1..]
+                .split_once('}')
+                .ok_or(KeyTemplateError::Unterminated)?;
+
+            segments.push(match name {
+                "key" => Segment::Key,
+                "iteration" => Segment::Iteration,
+                "stream" => Segment::Stream,
+                "copy" => Segment::Copy,
+                v => return Err(KeyTemplateError::UnknownPlaceholder(v.to_string())),
+            });
+
+            s = rest;
+        }
+
+        if !s.is_empty() {
+            segments.push(Segment::Literal(s.to_string()));
+        }
+
+        Ok(Self(segments))
+    }
+}
+
+/// Produces the rewritten copies of each message read during a pass over the
+/// source.
+#[derive(Debug)]
+pub(crate) struct Amplifier {
+    streams: usize,
+    key_template: Option<KeyTemplate>,
+    stamp_header: Option<String>,
+}
+
+impl Amplifier {
+    /// Initialise an amplifier for `opts`, returning [`None`] if messages are
+    /// copied unmodified, once per pass.
+    pub(crate) fn new(opts: &AmplifyOpts) -> Option<Self> {
+        if opts.streams <= 1 && opts.key_template.is_none() && opts.stamp_header.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            streams: opts.streams.max(1),
+            key_template: opts.key_template.clone(),
+            stamp_header: opts.stamp_header.clone(),
+        })
+    }
+
+    /// Return the copies of `msg` to write during the given source pass.
+    pub(crate) fn copies<'a>(
+        &'a self,
+        msg: &'a Message,
+        iteration: usize,
+    ) -> impl Iterator<Item = Message> + 'a {
+        (0..self.streams).map(move |stream| {
+            let copy = iteration * self.streams + stream;
+
+            let key = match &self.key_template {
+                Some(t) => Some(t.render(msg.key(), iteration, stream, copy)),
+                None => msg.key().map(ToOwned::to_owned),
+            };
+
+            let headers = match &self.stamp_header {
+                Some(name) => {
+                    let mut h = msg.headers().cloned().unwrap_or_default();
+                    h.insert(name.clone(), copy.to_string().into_bytes());
+                    Some(h)
+                }
+                None => msg.headers().cloned(),
+            };
+
+            Message::new(
+                msg.topic(),
+                msg.partition(),
+                msg.offset(),
+                msg.timestamp().cloned(),
+                headers,
+                key,
+                msg.payload().map(ToOwned::to_owned),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{cli::common::test_util::test_parse, message::test_util::message};
+
+    use super::*;
+
+    test_parse!(
+        suffix,
+        type = KeyTemplate,
+        input = "{key}-{copy}",
+        want = Ok(KeyTemplate(v)) if v == [
+            Segment::Key,
+            Segment::Literal("-".to_string()),
+            Segment::Copy,
+        ]
+    );
+
+    test_parse!(
+        literal,
+        type = KeyTemplate,
+        input = "static",
+        want = Ok(KeyTemplate(v)) if v == [Segment::Literal("static".to_string())]
+    );
+
+    test_parse!(
+        all,
+        type = KeyTemplate,
+        input = "a{iteration}b{stream}c",
+        want = Ok(KeyTemplate(v)) if v == [
+            Segment::Literal("a".to_string()),
+            Segment::Iteration,
+            Segment::Literal("b".to_string()),
+            Segment::Stream,
+            Segment::Literal("c".to_string()),
+        ]
+    );
+
+    test_parse!(
+        unknown,
+        type = KeyTemplate,
+        input = "{key}-{bananas}",
+        want = Err(KeyTemplateError::UnknownPlaceholder(v)) if v == "bananas"
+    );
+
+    test_parse!(
+        unterminated,
+        type = KeyTemplate,
+        input = "{key",
+        want = Err(KeyTemplateError::Unterminated)
+    );
+
+    #[test]
+    fn test_copies() {
+        let opts = AmplifyOpts {
+            repeat: 2,
+            streams: 3,
+            key_template: Some("{key}-{iteration}.{stream}".parse().unwrap()),
+            stamp_header: Some("copy".to_string()),
+        };
+        assert_eq!(opts.factor(), 6);
+
+        let a = Amplifier::new(&opts).expect("amplifier enabled");
+
+        let msg = message()
+            .offset(42)
+            .header("existing", "v")
+            .key(b"k".to_vec())
+            .payload("payload")
+            .build();
+
+        let got = a.copies(&msg, 1).collect::<Vec<_>>();
+        assert_eq!(got.len(), 3);
+
+        for (stream, m) in got.iter().enumerate() {
+            assert_eq!(m.key(), Some(format!("k-1.{}", stream).as_bytes()));
+            assert_eq!(m.payload(), msg.payload());
+            assert_eq!(m.offset(), 42);
+
+            let h = m.headers().unwrap();
+            assert_eq!(h["existing"], b"v");
+            assert_eq!(h["copy"], (3 + stream).to_string().into_bytes());
+        }
+    }
+
+    #[test]
+    fn test_copies_existing_stamp_header() {
+        let opts = AmplifyOpts {
+            streams: 2,
+            stamp_header: Some("copy".to_string()),
+            ..Default::default()
+        };
+
+        let a = Amplifier::new(&opts).expect("amplifier enabled");
+
+        let msg = message().header("copy", "upstream").build();
+
+        // An existing header is replaced, keeping each copy distinct.
+        let got = a
+            .copies(&msg, 1)
+            .map(|m| m.headers().unwrap()["copy"].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(got, [b"2".to_vec(), b"3".to_vec()]);
+    }
+
+    #[test]
+    fn test_copies_no_key() {
+        let opts = AmplifyOpts {
+            key_template: Some("{key}x{copy}".parse().unwrap()),
+            ..Default::default()
+        };
+
+        let a = Amplifier::new(&opts).expect("amplifier enabled");
+        let msg = message().build();
+
+        let got = a.copies(&msg, 4).collect::<Vec<_>>();
+        assert_eq!(got.len(), 1);
+        assert_eq!(got[0].key(), Some(b"x4".as_slice()));
+        assert_eq!(got[0].headers(), None);
+    }
+
+    #[test]
+    fn test_disabled() {
+        assert!(Amplifier::new(&AmplifyOpts::default()).is_none());
+        assert!(Amplifier::new(&AmplifyOpts {
+            repeat: 10,
+            ..Default::default()
+        })
+        .is_none());
+    }
+}
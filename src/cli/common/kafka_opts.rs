@@ -1,6 +1,6 @@
 use std::{num::ParseIntError, str::FromStr, time::Duration};
 
-use clap::Args;
+use clap::{ArgEnum, Args};
 use rdkafka::ClientConfig;
 
 // NOTE: this is shared with all kafka commands, including metadata.
@@ -11,10 +11,24 @@ pub struct KafkaOpts {
     #[clap(long, default_value = "10", parse(try_from_str = parse_seconds))]
     pub timeout: Duration,
 
-    /// The Kafka producer group ID to use.
+    /// The Kafka consumer group ID to use.
+    ///
+    /// Offsets are only read from, and committed to this group when
+    /// explicitly requested (such as with "cp --commit").
     #[clap(long, default_value = "bananas")]
     pub group: String,
 
+    /// The transaction isolation level used when consuming messages.
+    ///
+    /// With "read_committed", messages from aborted (or still open)
+    /// transactions are never returned. With "read_uncommitted", all messages
+    /// are returned, including those from aborted transactions.
+    ///
+    /// In both cases transaction control markers occupy an offset but are
+    /// never returned, leaving gaps in the consumed offsets.
+    #[clap(long, arg_enum, default_value = "read_committed")]
+    pub isolation: IsolationLevel,
+
     /// Arbitrary Kafka parameters for customised configuration.
     ///
     /// Any Kafka client configuration parameter accepted by librdkafka can be
@@ -38,6 +52,32 @@ impl KafkaOpts {
 
         config
     }
+
+    /// Return the configuration for a consumer, reading transactional messages
+    /// with the configured isolation level.
+    pub(crate) fn new_consumer_config(&self, brokers: Vec<String>) -> ClientConfig {
+        let mut config = self.new_kafka_config(brokers);
+        config.set("isolation.level", self.isolation.as_str());
+        config
+    }
+}
+
+/// The visibility of transactional messages to a consumer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ArgEnum)]
+#[clap(rename_all = "snake_case")]
+pub enum IsolationLevel {
+    ReadCommitted,
+    ReadUncommitted,
+}
+
+impl IsolationLevel {
+    /// The librdkafka configuration value for this isolation level.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IsolationLevel::ReadCommitted => "read_committed",
+            IsolationLevel::ReadUncommitted => "read_uncommitted",
+        }
+    }
 }
 
 fn parse_seconds(input: &str) -> Result<Duration, ParseIntError> {
@@ -1,7 +1,23 @@
+mod amplify;
 mod kafka_opts;
 mod offset;
+mod produce_opts;
+mod provenance;
+mod replay;
+mod route;
+mod table;
 mod target;
+#[cfg(test)]
+pub(crate) mod test_util;
+mod throttle;
 
+pub use amplify::*;
 pub use kafka_opts::*;
 pub use offset::*;
+pub use produce_opts::*;
+pub use provenance::*;
+pub use replay::*;
+pub use route::*;
+pub use table::*;
 pub use target::*;
+pub use throttle::*;
@@ -1,9 +1,19 @@
-use std::{cmp::Ordering, num::ParseIntError, str::FromStr};
+use std::{
+    cmp::Ordering,
+    num::ParseIntError,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use chrono::DateTime;
 
 use clap::Args;
 use thiserror::Error;
 
-use crate::message::{Message, Timestamp};
+use crate::{
+    message::{Message, Timestamp},
+    source::StartPosition,
+};
 
 #[derive(Debug, Args, Clone)]
 pub(crate) struct OffsetClap {
@@ -29,11 +39,33 @@ pub(crate) struct OffsetClap {
     /// Restrict messages read from the source to the specified producer
     /// timestamps.
     ///
-    /// Timestamps should be formatted as unix timestamps (seconds since epoch),
-    /// and have the same format as offset ranges. Unlike offsets, a kafka
-    /// consumer cannot seek based on the timestamp and instead client-side
-    /// filtering is performed.
-    #[clap(short, long, parse(try_from_str), conflicts_with = "offset")]
+    /// Ranges are inclusive and in the form "start", or with an optional end
+    /// as "start:end". Each bound is one of:
+    ///
+    ///   - unix seconds: "1714557600", or "1714557600s"
+    ///
+    ///   - unix milliseconds: "1714557600000ms"
+    ///
+    ///   - an RFC 3339 timestamp: "2024-05-01T10:00:00Z", "2024-05-01T10:00Z"
+    ///
+    ///   - a duration before now: "-2h", "-30m", "-90s", "-500ms", "-1d"
+    ///
+    ///   - a duration after now, or after the start when used as the end:
+    ///     "+15m"
+    ///
+    /// For example, read the last 2 hours: "-2h:", or 15 minutes from 10:00
+    /// UTC: "2024-05-01T10:00Z:+15m".
+    ///
+    /// For Kafka sources, the broker is queried for the first offset at or
+    /// after the start timestamp and the consumer seeks directly to it.
+    /// Reading stops at the first message after the end timestamp.
+    #[clap(
+        short,
+        long,
+        parse(try_from_str),
+        conflicts_with = "offset",
+        allow_hyphen_values = true
+    )]
     time_range: Option<TimeRange>,
 }
 
@@ -48,25 +80,52 @@ impl OffsetClap {
         range.cmp(msg)
     }
 
-    /// Return the minimum offset to read, if known.
-    pub(crate) fn start_offset(&self) -> Option<i64> {
+    /// Return the position of the first message to read, if known.
+    pub(crate) fn start_position(&self) -> Option<StartPosition> {
         if let Some(offset) = self.offset {
-            return Some(offset.start);
+            return Some(StartPosition::Offset(offset.start));
+        }
+
+        if let Some(range) = self.time_range {
+            return Some(StartPosition::Timestamp(range.start));
         }
 
         None
     }
 
+    /// Return the offset reading a partition with the high watermark `high`
+    /// stops before, or [`None`] if it depends on message timestamps.
+    pub(crate) fn end_offset(&self, high: i64) -> Option<i64> {
+        if self.time_range.is_some_and(|v| v.end.is_some()) {
+            return None;
+        }
+
+        Some(match self.offset.and_then(|v| v.end) {
+            Some(end) => high.min(end + 1),
+            None => high,
+        })
+    }
+
     /// Wrap the provided iter in an adaptor to limit messages to this offset
     /// range, if any.
     pub fn wrap_iter<I>(&self, iter: I) -> OffsetAwareIter<I> {
-        OffsetAwareIter(iter, self.clone())
+        OffsetAwareIter(iter, self.clone(), false)
+    }
+
+    /// Like [`OffsetClap::wrap_iter`], but for sources that interleave messages
+    /// from more than one partition.
+    ///
+    /// Reaching the end of the range in one partition says nothing about the
+    /// others, so messages beyond the end of the range are skipped rather than
+    /// ending the iterator.
+    pub fn wrap_multi_partition_iter<I>(&self, iter: I) -> OffsetAwareIter<I> {
+        OffsetAwareIter(iter, self.clone(), true)
     }
 }
 
 /// An iterator adaptor that constrains the output messages to those that match
 /// the configured offset range, if any.
-pub(crate) struct OffsetAwareIter<I>(I, OffsetClap);
+pub(crate) struct OffsetAwareIter<I>(I, OffsetClap, bool);
 
 impl<I> Iterator for OffsetAwareIter<I>
 where
@@ -80,7 +139,11 @@ where
                 Ok(v) if self.1.cmp(&v).is_none() => {
                     panic!("cannot compare offset");
                 }
-                Ok(v) if self.1.cmp(&v) == Some(Ordering::Greater) => return None,
+                Ok(v) if self.1.cmp(&v) == Some(Ordering::Greater) => {
+                    if !self.2 {
+                        return None;
+                    }
+                }
                 Ok(v) if self.1.cmp(&v) == Some(Ordering::Less) => {
                     eprintln!("[-] skipping offset {}", v.offset());
                 }
@@ -101,9 +164,21 @@ pub enum OffsetError {
 
     #[error("invalid offset range format (expected 'start', or 'start:end')")]
     TooManyParts,
+
+    #[error(
+        "invalid timestamp '{}' (expected unix seconds, '<n>s', '<n>ms', or an RFC 3339 timestamp)",
+        .0
+    )]
+    InvalidTimestamp(String),
+
+    #[error(
+        "invalid relative time '{}' (expected a signed duration such as '-2h' or '+15m')",
+        .0
+    )]
+    InvalidDuration(String),
 }
 
-// TODO: doc inclusive
+/// An inclusive range of message offsets.
 #[derive(Debug, PartialEq, Default, Eq, Clone, Copy)]
 pub(crate) struct OffsetRange {
     start: i64,
@@ -135,7 +210,7 @@ impl FromStr for OffsetRange {
     }
 }
 
-// TODO: doc inclusive
+/// An inclusive range of message timestamps, in milliseconds since epoch.
 #[derive(Debug, PartialEq, Default, Eq, Clone, Copy)]
 pub(crate) struct TimeRange {
     start: i64,
@@ -161,19 +236,179 @@ impl TimeRange {
 
         Some(Ordering::Equal)
     }
-}
 
-// TODO(dom:test): TimeRange
-// TODO(dom:test): TimeRange::cmp
-// TODO(dom:test): OffsetRange::cmp
+    /// Parse `s`, resolving any relative bounds against `now` (milliseconds
+    /// since epoch).
+    fn parse_at(s: &str, now: i64) -> Result<Self, OffsetError> {
+        let (start, end) = split_time_range(s)?;
+
+        let start = match start {
+            None => 0,
+            Some(TimeBound::Absolute(v)) => v,
+            Some(TimeBound::Ago(v)) => now - v,
+            Some(TimeBound::Ahead(v)) => now + v,
+        };
+
+        let end = end.map(|v| match v {
+            TimeBound::Absolute(v) => v,
+            TimeBound::Ago(v) => now - v,
+            TimeBound::Ahead(v) => start + v,
+        });
+
+        Ok(TimeRange { start, end })
+    }
+}
 
 impl FromStr for TimeRange {
     type Err = OffsetError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (start, end) = parse(s)?;
-        Ok(TimeRange { start, end })
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system clock is before unix epoch")
+            .as_millis() as i64;
+
+        Self::parse_at(s, now)
+    }
+}
+
+/// A single bound of a [`TimeRange`], prior to resolving relative values.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum TimeBound {
+    /// Milliseconds since epoch.
+    Absolute(i64),
+
+    /// A number of milliseconds before now.
+    Ago(i64),
+
+    /// A number of milliseconds after now, or after the range start when used
+    /// as the end bound.
+    Ahead(i64),
+}
+
+impl FromStr for TimeBound {
+    type Err = OffsetError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(v) = s.strip_prefix('-') {
+            return parse_duration(v).map(TimeBound::Ago);
+        }
+        if let Some(v) = s.strip_prefix('+') {
+            return parse_duration(v).map(TimeBound::Ahead);
+        }
+
+        parse_timestamp(s).map(TimeBound::Absolute)
+    }
+}
+
+/// Parse an absolute timestamp, or a signed duration relative to now (such as
+/// "-2h"), into milliseconds since epoch.
+pub(crate) fn parse_time(s: &str) -> Result<i64, OffsetError> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system clock is before unix epoch")
+        .as_millis() as i64;
+
+    parse_time_at(s, now)
+}
+
+/// Parse `s` as [`parse_time`] does, resolving relative durations against
+/// `now`.
+fn parse_time_at(s: &str, now: i64) -> Result<i64, OffsetError> {
+    Ok(match s.parse::<TimeBound>()? {
+        TimeBound::Absolute(v) => v,
+        TimeBound::Ago(v) => now - v,
+        TimeBound::Ahead(v) => now + v,
+    })
+}
+
+/// Parse an absolute timestamp into milliseconds since epoch.
+///
+/// Accepts unix seconds, "<n>s", "<n>ms", or an RFC 3339 timestamp (with
+/// optional seconds).
+pub(crate) fn parse_timestamp(s: &str) -> Result<i64, OffsetError> {
+    let invalid = || OffsetError::InvalidTimestamp(s.to_string());
+
+    // Unix timestamps, either in seconds (the default) or with an explicit
+    // unit suffix.
+    if let Some(Ok(v)) = s.strip_suffix("ms").map(i64::from_str) {
+        return Ok(v);
+    }
+    if let Ok(v) = s.strip_suffix('s').unwrap_or(s).parse::<i64>() {
+        return v.checked_mul(1000).ok_or_else(invalid);
+    }
+
+    // Otherwise attempt to parse it as an RFC 3339 timestamp, allowing the
+    // seconds to be omitted.
+    DateTime::parse_from_rfc3339(s)
+        .or_else(|_| DateTime::parse_from_str(s, "%Y-%m-%dT%H:%M%#z"))
+        .map(|v| v.timestamp_millis())
+        .map_err(|_| invalid())
+}
+
+/// Parse an unsigned duration with an optional unit suffix (defaulting to
+/// seconds) into a number of milliseconds.
+pub(crate) fn parse_duration(s: &str) -> Result<i64, OffsetError> {
+    let invalid = || OffsetError::InvalidDuration(s.to_string());
+
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (n, unit) = s.split_at(split);
+
+    let n = n.parse::<i64>().map_err(|_| invalid())?;
+    let multiplier = match unit {
+        "ms" => 1,
+        "" | "s" => 1000,
+        "m" => 60 * 1000,
+        "h" => 60 * 60 * 1000,
+        "d" => 24 * 60 * 60 * 1000,
+        _ => return Err(invalid()),
+    };
+
+    n.checked_mul(multiplier).ok_or_else(invalid)
+}
+
+/// Split a time range expression into its (optional) start and end bounds.
+///
+/// Unlike offset ranges, the ':' separator cannot be found by a naive split as
+/// RFC 3339 timestamps contain ':' characters themselves. Instead each
+/// candidate separator is tried in turn, selecting the first that produces two
+/// valid bounds.
+fn split_time_range(s: &str) -> Result<(Option<TimeBound>, Option<TimeBound>), OffsetError> {
+    let bound = |v: &str| {
+        if v.is_empty() {
+            return Ok(None);
+        }
+        v.parse::<TimeBound>().map(Some)
+    };
+
+    // The entire input may be a single start bound.
+    let whole_err = match bound(s) {
+        Ok(v) => return Ok((v, None)),
+        Err(e) => e,
+    };
+
+    // The most relevant error is one where the other half of the split was
+    // valid, falling back to the first error observed.
+    let mut first_err = None;
+    let mut partial_err = None;
+    for (i, _) in s.match_indices(':') {
+        match (bound(&s[..i]), bound(&s[i + 1..])) {
+            (Ok(start), Ok(end)) => return Ok((start, end)),
+            (Ok(_), Err(e)) | (Err(e), Ok(_)) => {
+                partial_err.get_or_insert(e);
+            }
+            (Err(e), Err(_)) => {
+                first_err.get_or_insert(e);
+            }
+        }
+    }
+
+    // If every ':' delimited part is valid, there are simply too many of them.
+    if s.contains(':') && s.split(':').all(|v| bound(v).is_ok()) {
+        return Err(OffsetError::TooManyParts);
     }
+
+    Err(partial_err.or(first_err).unwrap_or(whole_err))
 }
 
 fn parse(s: &str) -> Result<(i64, Option<i64>), OffsetError> {
@@ -199,6 +434,8 @@ fn parse(s: &str) -> Result<(i64, Option<i64>), OffsetError> {
 
 #[cfg(test)]
 mod tests {
+    use crate::message::test_util::message;
+
     use super::*;
 
     use assert_matches::assert_matches;
@@ -317,4 +554,506 @@ mod tests {
             end: Some(12),
         })
     );
+
+    /// The fixed "now" used to resolve relative time expressions in tests
+    /// (2024-05-01T12:00:00Z).
+    const NOW: i64 = 1_714_564_800_000;
+
+    macro_rules! test_parse_time {
+        (
+			$name:ident,
+			input = $input:literal,
+			want = $($want:tt)+
+		) => {
+            paste::paste! {
+                #[test]
+                fn [<test_parse_time_ $name>]() {
+                    let input: &str = $input;
+                    assert_matches!(TimeRange::parse_at(input, NOW), $($want)+);
+                }
+            }
+        };
+        (
+			$name:ident,
+			instant = $input:literal,
+			want = $($want:tt)+
+		) => {
+            paste::paste! {
+                #[test]
+                fn [<test_parse_time_instant_ $name>]() {
+                    let input: &str = $input;
+                    assert_matches!(parse_time_at(input, NOW), $($want)+);
+                }
+            }
+        };
+    }
+
+    test_parse_time!(
+        unix_seconds,
+        input = "1714557600",
+        want = Ok(TimeRange {
+            start: 1_714_557_600_000,
+            end: None
+        })
+    );
+
+    test_parse_time!(
+        unix_seconds_suffix,
+        input = "1714557600s",
+        want = Ok(TimeRange {
+            start: 1_714_557_600_000,
+            end: None
+        })
+    );
+
+    test_parse_time!(
+        unix_millis_suffix,
+        input = "1714557600123ms",
+        want = Ok(TimeRange {
+            start: 1_714_557_600_123,
+            end: None
+        })
+    );
+
+    test_parse_time!(
+        unix_start_and_end,
+        input = "1714557600:1714557660",
+        want = Ok(TimeRange {
+            start: 1_714_557_600_000,
+            end: Some(1_714_557_660_000)
+        })
+    );
+
+    test_parse_time!(
+        mixed_units,
+        input = "1714557600s:1714557660000ms",
+        want = Ok(TimeRange {
+            start: 1_714_557_600_000,
+            end: Some(1_714_557_660_000)
+        })
+    );
+
+    test_parse_time!(
+        end_only,
+        input = ":1714557600",
+        want = Ok(TimeRange {
+            start: 0,
+            end: Some(1_714_557_600_000)
+        })
+    );
+
+    test_parse_time!(
+        unspecified,
+        input = "",
+        want = Ok(TimeRange {
+            start: 0,
+            end: None
+        })
+    );
+
+    test_parse_time!(
+        empty_parts,
+        input = ":",
+        want = Ok(TimeRange {
+            start: 0,
+            end: None
+        })
+    );
+
+    test_parse_time!(
+        rfc3339,
+        input = "2024-05-01T10:00:00Z",
+        want = Ok(TimeRange {
+            start: 1_714_557_600_000,
+            end: None
+        })
+    );
+
+    test_parse_time!(
+        rfc3339_no_seconds,
+        input = "2024-05-01T10:00Z",
+        want = Ok(TimeRange {
+            start: 1_714_557_600_000,
+            end: None
+        })
+    );
+
+    test_parse_time!(
+        rfc3339_offset,
+        input = "2024-05-01T11:00:00+01:00",
+        want = Ok(TimeRange {
+            start: 1_714_557_600_000,
+            end: None
+        })
+    );
+
+    test_parse_time!(
+        rfc3339_fractional,
+        input = "2024-05-01T10:00:00.250Z",
+        want = Ok(TimeRange {
+            start: 1_714_557_600_250,
+            end: None
+        })
+    );
+
+    test_parse_time!(
+        rfc3339_start_and_end,
+        input = "2024-05-01T10:00:00Z:2024-05-01T10:01Z",
+        want = Ok(TimeRange {
+            start: 1_714_557_600_000,
+            end: Some(1_714_557_660_000)
+        })
+    );
+
+    test_parse_time!(
+        relative_start,
+        input = "-2h:",
+        want = Ok(TimeRange {
+            start: 1_714_557_600_000,
+            end: None
+        })
+    );
+
+    test_parse_time!(
+        relative_start_no_separator,
+        input = "-2h",
+        want = Ok(TimeRange {
+            start: 1_714_557_600_000,
+            end: None
+        })
+    );
+
+    test_parse_time!(
+        relative_start_and_end,
+        input = "-2h:-1h",
+        want = Ok(TimeRange {
+            start: 1_714_557_600_000,
+            end: Some(1_714_561_200_000)
+        })
+    );
+
+    test_parse_time!(
+        relative_units,
+        input = "-1d:-500ms",
+        want = Ok(TimeRange {
+            start: 1_714_478_400_000,
+            end: Some(1_714_564_799_500)
+        })
+    );
+
+    test_parse_time!(
+        relative_default_seconds,
+        input = "-90",
+        want = Ok(TimeRange {
+            start: 1_714_564_710_000,
+            end: None
+        })
+    );
+
+    test_parse_time!(
+        rfc3339_plus_duration,
+        input = "2024-05-01T10:00Z:+15m",
+        want = Ok(TimeRange {
+            start: 1_714_557_600_000,
+            end: Some(1_714_558_500_000)
+        })
+    );
+
+    test_parse_time!(
+        relative_start_plus_duration,
+        input = "-2h:+30s",
+        want = Ok(TimeRange {
+            start: 1_714_557_600_000,
+            end: Some(1_714_557_630_000)
+        })
+    );
+
+    test_parse_time!(
+        invalid_start,
+        input = "bananas",
+        want = Err(OffsetError::InvalidTimestamp(v)) => {
+            assert_eq!(v, "bananas");
+        }
+    );
+
+    test_parse_time!(
+        invalid_end,
+        input = "2024-05-01T10:00Z:bananas",
+        want = Err(OffsetError::InvalidTimestamp(v)) => {
+            assert_eq!(v, "bananas");
+        }
+    );
+
+    test_parse_time!(
+        invalid_duration_unit,
+        input = "-2w:",
+        want = Err(OffsetError::InvalidDuration(v)) => {
+            assert_eq!(v, "2w");
+        }
+    );
+
+    test_parse_time!(
+        invalid_duration_empty,
+        input = "-:",
+        want = Err(OffsetError::InvalidDuration(_))
+    );
+
+    test_parse_time!(
+        overflow,
+        input = "9223372036854775807",
+        want = Err(OffsetError::InvalidTimestamp(_))
+    );
+
+    test_parse_time!(
+        too_many_parts,
+        input = "1:2:3",
+        want = Err(OffsetError::TooManyParts)
+    );
+
+    test_parse_time!(
+        unix_seconds,
+        instant = "1714557600",
+        want = Ok(1_714_557_600_000)
+    );
+
+    test_parse_time!(
+        unix_millis_suffix,
+        instant = "1714557600123ms",
+        want = Ok(1_714_557_600_123)
+    );
+
+    test_parse_time!(
+        rfc3339,
+        instant = "2024-05-01T10:00:00Z",
+        want = Ok(1_714_557_600_000)
+    );
+
+    test_parse_time!(
+        ago,
+        instant = "-2h",
+        want = Ok(v) if v == NOW - 2 * 60 * 60 * 1000
+    );
+
+    test_parse_time!(
+        ahead,
+        instant = "+30s",
+        want = Ok(v) if v == NOW + 30 * 1000
+    );
+
+    test_parse_time!(
+        invalid_duration,
+        instant = "-2y",
+        want = Err(OffsetError::InvalidDuration(_))
+    );
+
+    test_parse_time!(
+        invalid_timestamp,
+        instant = "bananas",
+        want = Err(OffsetError::InvalidTimestamp(_))
+    );
+
+    test_parse_time!(
+        range_rejected,
+        instant = "1714557600:1714557660",
+        want = Err(_)
+    );
+
+    macro_rules! test_cmp {
+        (
+			$name:ident,
+			range = $range:expr,
+			msg = $msg:expr,
+			want = $want:expr
+		) => {
+            paste::paste! {
+                #[test]
+                fn [<test_cmp_ $name>]() {
+                    let msg: Message = $msg;
+                    assert_eq!($range.cmp(&msg), $want);
+                }
+            }
+        };
+    }
+
+    fn at_offset(offset: i64) -> Message {
+        message().offset(offset).build()
+    }
+
+    fn at_time(ts: Option<Timestamp>) -> Message {
+        message().timestamp(ts).build()
+    }
+
+    test_cmp!(
+        offset_before_start,
+        range = OffsetRange {
+            start: 10,
+            end: Some(20)
+        },
+        msg = at_offset(9),
+        want = Some(Ordering::Less)
+    );
+
+    test_cmp!(
+        offset_start_inclusive,
+        range = OffsetRange {
+            start: 10,
+            end: Some(20)
+        },
+        msg = at_offset(10),
+        want = Some(Ordering::Equal)
+    );
+
+    test_cmp!(
+        offset_end_inclusive,
+        range = OffsetRange {
+            start: 10,
+            end: Some(20)
+        },
+        msg = at_offset(20),
+        want = Some(Ordering::Equal)
+    );
+
+    test_cmp!(
+        offset_after_end,
+        range = OffsetRange {
+            start: 10,
+            end: Some(20)
+        },
+        msg = at_offset(21),
+        want = Some(Ordering::Greater)
+    );
+
+    test_cmp!(
+        offset_unbounded_end,
+        range = OffsetRange {
+            start: 10,
+            end: None
+        },
+        msg = at_offset(i64::MAX),
+        want = Some(Ordering::Equal)
+    );
+
+    test_cmp!(
+        time_before_start,
+        range = TimeRange {
+            start: 1000,
+            end: Some(2000)
+        },
+        msg = at_time(Some(Timestamp::CreateTime(999))),
+        want = Some(Ordering::Less)
+    );
+
+    test_cmp!(
+        time_start_inclusive,
+        range = TimeRange {
+            start: 1000,
+            end: Some(2000)
+        },
+        msg = at_time(Some(Timestamp::CreateTime(1000))),
+        want = Some(Ordering::Equal)
+    );
+
+    test_cmp!(
+        time_end_inclusive,
+        range = TimeRange {
+            start: 1000,
+            end: Some(2000)
+        },
+        msg = at_time(Some(Timestamp::LogAppendTime(2000))),
+        want = Some(Ordering::Equal)
+    );
+
+    test_cmp!(
+        time_after_end,
+        range = TimeRange {
+            start: 1000,
+            end: Some(2000)
+        },
+        msg = at_time(Some(Timestamp::LogAppendTime(2001))),
+        want = Some(Ordering::Greater)
+    );
+
+    test_cmp!(
+        time_unbounded_end,
+        range = TimeRange {
+            start: 1000,
+            end: None
+        },
+        msg = at_time(Some(Timestamp::CreateTime(i64::MAX))),
+        want = Some(Ordering::Equal)
+    );
+
+    test_cmp!(
+        time_no_timestamp,
+        range = TimeRange {
+            start: 1000,
+            end: None
+        },
+        msg = at_time(None),
+        want = None
+    );
+
+    #[test]
+    fn test_end_offset() {
+        let clap = |offset, time_range| OffsetClap { offset, time_range };
+
+        assert_eq!(clap(None, None).end_offset(42), Some(42));
+        assert_eq!(
+            clap(
+                Some(OffsetRange {
+                    start: 0,
+                    end: None
+                }),
+                None
+            )
+            .end_offset(42),
+            Some(42)
+        );
+        assert_eq!(
+            clap(
+                Some(OffsetRange {
+                    start: 0,
+                    end: Some(10)
+                }),
+                None
+            )
+            .end_offset(42),
+            Some(11)
+        );
+        assert_eq!(
+            clap(
+                Some(OffsetRange {
+                    start: 0,
+                    end: Some(100)
+                }),
+                None
+            )
+            .end_offset(42),
+            Some(42)
+        );
+
+        // Reads ending at a timestamp stop at an unknown offset.
+        assert_eq!(
+            clap(
+                None,
+                Some(TimeRange {
+                    start: 0,
+                    end: None
+                })
+            )
+            .end_offset(42),
+            Some(42)
+        );
+        assert_eq!(
+            clap(
+                None,
+                Some(TimeRange {
+                    start: 0,
+                    end: Some(10)
+                })
+            )
+            .end_offset(42),
+            None
+        );
+    }
 }
@@ -0,0 +1,314 @@
+use std::{num::ParseIntError, path::PathBuf, str::FromStr};
+
+use clap::Args;
+use thiserror::Error;
+
+use crate::message::Timestamp;
+
+use super::{parse_duration, OffsetError};
+
+/// Options controlling how messages are written to a Kafka sink.
+#[derive(Debug, Default, Args)]
+pub struct ProduceOpts {
+    /// The timestamp applied to messages written to Kafka.
+    ///
+    /// Supports either:
+    ///
+    ///   - "original": the timestamp of the source message (the default)
+    ///
+    ///   - "now": the time the message is produced
+    ///
+    ///   - "shift:<duration>": the original timestamp moved by a signed
+    ///     duration, such as "shift:+1d" or "shift:-90m"
+    ///
+    /// Source messages with a LogAppendTime timestamp are produced with that
+    /// value as their CreateTime. If the destination topic is configured with
+    /// "message.timestamp.type=LogAppendTime", the broker replaces any
+    /// producer timestamp with its own. Messages without a timestamp are
+    /// always produced with the current time.
+    #[clap(long, default_value = "original")]
+    pub timestamps: TimestampMode,
+
+    /// The strategy used to choose the destination partition of each message.
+    ///
+    /// Supports either:
+    ///
+    ///   - "default": librdkafka's default partitioner (the default)
+    ///
+    ///   - "preserve": the partition the message was read from
+    ///
+    ///   - "fixed:<n>": always partition n
+    ///
+    ///   - "round-robin": cycle through all destination partitions
+    ///
+    ///   - "murmur2": hash the message key using murmur2, matching the
+    ///     placement of the Java client's default partitioner. Messages
+    ///     without a key are distributed round-robin.
+    ///
+    ///   - "modulo": the source partition modulo the destination partition
+    ///     count, for copying between topics with differing partition counts
+    ///
+    /// Cannot be used when the sink specifies a partition.
+    #[clap(long, default_value = "default")]
+    pub partitioner: Partitioner,
+
+    /// Write messages that fail to be delivered to Kafka to this file, so
+    /// they can be retried later with "ktool cp".
+    ///
+    /// The file is only created if a delivery fails.
+    #[clap(long)]
+    pub failed_messages: Option<PathBuf>,
+
+    /// Write messages to Kafka using transactions that also commit the
+    /// consumed source offsets for the consumer group (--group).
+    ///
+    /// Each transaction atomically writes a batch of messages and records
+    /// the source position they were read from, so an interrupted copy can
+    /// be restarted without duplicating or skipping any messages. The copy
+    /// resumes from the offsets committed by the previous run.
+    ///
+    /// The transactional.id defaults to "ktool-<group>", so only one copy per
+    /// group can run at a time. Requires both a Kafka source and sink.
+    #[clap(long)]
+    pub exactly_once: bool,
+
+    /// The maximum number of messages written in each transaction when using
+    /// --exactly-once.
+    #[clap(long, default_value = "1000")]
+    pub transaction_size: usize,
+}
+
+#[derive(Debug, Error)]
+pub enum PartitionerError {
+    #[error("invalid partition: {}", .0)]
+    ParseInt(#[from] ParseIntError),
+
+    #[error(
+        "invalid partitioner (expected 'default', 'preserve', 'fixed:<n>', 'round-robin', \
+        'murmur2', or 'modulo')"
+    )]
+    Invalid,
+}
+
+/// A strategy for selecting the destination partition of a message.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Partitioner {
+    /// Defer to the librdkafka partitioner.
+    #[default]
+    Default,
+
+    /// Use the partition the message was read from.
+    Preserve,
+
+    /// Write all messages to a single partition.
+    Fixed(i32),
+
+    /// Cycle through all destination partitions.
+    RoundRobin,
+
+    /// Hash the message key with murmur2, compatible with the Java client.
+    Murmur2,
+
+    /// Map the source partition onto the destination partitions by modulo.
+    Modulo,
+}
+
+impl Partitioner {
+    /// Returns true if this strategy requires the number of partitions in the
+    /// destination topic.
+    pub fn needs_partition_count(&self) -> bool {
+        matches!(
+            self,
+            Partitioner::Preserve
+                | Partitioner::RoundRobin
+                | Partitioner::Murmur2
+                | Partitioner::Modulo
+        )
+    }
+}
+
+impl FromStr for Partitioner {
+    type Err = PartitionerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(v) = s.strip_prefix("fixed:") {
+            return Ok(Self::Fixed(v.parse()?));
+        }
+
+        match s {
+            "default" => Ok(Self::Default),
+            "preserve" => Ok(Self::Preserve),
+            "round-robin" => Ok(Self::RoundRobin),
+            "murmur2" => Ok(Self::Murmur2),
+            "modulo" => Ok(Self::Modulo),
+            _ => Err(PartitionerError::Invalid),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum TimestampModeError {
+    #[error("invalid timestamp shift: {}", .0)]
+    Shift(#[from] OffsetError),
+
+    #[error("invalid timestamp mode (expected 'original', 'now', or 'shift:<duration>')")]
+    Invalid,
+}
+
+/// The timestamp to apply to a message produced to Kafka.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampMode {
+    /// Preserve the timestamp of the source message.
+    #[default]
+    Original,
+
+    /// Let the producer assign the current time.
+    Now,
+
+    /// Preserve the source timestamp, shifted by this many milliseconds.
+    Shift(i64),
+}
+
+impl TimestampMode {
+    /// Return the timestamp (milliseconds since epoch) to produce a message
+    /// with, given its original `ts`.
+    ///
+    /// [`None`] leaves the timestamp to the producer, which uses the current
+    /// time.
+    pub fn apply(&self, ts: Option<&Timestamp>) -> Option<i64> {
+        let ts = match ts? {
+            Timestamp::CreateTime(v) => *v,
+            Timestamp::LogAppendTime(v) => *v,
+        };
+
+        match self {
+            TimestampMode::Original => Some(ts),
+            TimestampMode::Now => None,
+            TimestampMode::Shift(v) => Some(ts + v),
+        }
+    }
+}
+
+impl FromStr for TimestampMode {
+    type Err = TimestampModeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(shift) = s.strip_prefix("shift:") {
+            let v = match shift.strip_prefix('-') {
+                Some(v) => -parse_duration(v)?,
+                None => parse_duration(shift.strip_prefix('+').unwrap_or(shift))?,
+            };
+            return Ok(Self::Shift(v));
+        }
+
+        match s {
+            "original" => Ok(Self::Original),
+            "now" => Ok(Self::Now),
+            _ => Err(TimestampModeError::Invalid),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::common::test_util::test_parse;
+
+    use super::*;
+
+    test_parse!(
+        original,
+        type = TimestampMode,
+        input = "original",
+        want = Ok(TimestampMode::Original)
+    );
+
+    test_parse!(now, type = TimestampMode, input = "now", want = Ok(TimestampMode::Now));
+
+    test_parse!(
+        shift_forward,
+        type = TimestampMode,
+        input = "shift:+1d",
+        want = Ok(TimestampMode::Shift(86_400_000))
+    );
+
+    test_parse!(
+        shift_unsigned,
+        type = TimestampMode,
+        input = "shift:90m",
+        want = Ok(TimestampMode::Shift(5_400_000))
+    );
+
+    test_parse!(
+        shift_backward,
+        type = TimestampMode,
+        input = "shift:-500ms",
+        want = Ok(TimestampMode::Shift(-500))
+    );
+
+    test_parse!(
+        shift_invalid,
+        type = TimestampMode,
+        input = "shift:bananas",
+        want = Err(TimestampModeError::Shift(_))
+    );
+
+    test_parse!(
+        invalid,
+        type = TimestampMode,
+        input = "bananas",
+        want = Err(TimestampModeError::Invalid)
+    );
+
+    test_parse!(default, type = Partitioner, input = "default", want = Ok(Partitioner::Default));
+
+    test_parse!(
+        preserve,
+        type = Partitioner,
+        input = "preserve",
+        want = Ok(Partitioner::Preserve)
+    );
+
+    test_parse!(fixed, type = Partitioner, input = "fixed:42", want = Ok(Partitioner::Fixed(42)));
+
+    test_parse!(
+        fixed_invalid,
+        type = Partitioner,
+        input = "fixed:bananas",
+        want = Err(PartitionerError::ParseInt(_))
+    );
+
+    test_parse!(
+        round_robin,
+        type = Partitioner,
+        input = "round-robin",
+        want = Ok(Partitioner::RoundRobin)
+    );
+
+    test_parse!(murmur2, type = Partitioner, input = "murmur2", want = Ok(Partitioner::Murmur2));
+
+    test_parse!(modulo, type = Partitioner, input = "modulo", want = Ok(Partitioner::Modulo));
+
+    test_parse!(
+        invalid,
+        type = Partitioner,
+        input = "bananas",
+        want = Err(PartitionerError::Invalid)
+    );
+
+    #[test]
+    fn test_apply() {
+        let create = Timestamp::CreateTime(1000);
+        let append = Timestamp::LogAppendTime(2000);
+
+        assert_eq!(TimestampMode::Original.apply(Some(&create)), Some(1000));
+        assert_eq!(TimestampMode::Original.apply(Some(&append)), Some(2000));
+        assert_eq!(TimestampMode::Original.apply(None), None);
+
+        assert_eq!(TimestampMode::Now.apply(Some(&create)), None);
+        assert_eq!(TimestampMode::Now.apply(None), None);
+
+        assert_eq!(TimestampMode::Shift(-10).apply(Some(&create)), Some(990));
+        assert_eq!(TimestampMode::Shift(10).apply(Some(&append)), Some(2010));
+        assert_eq!(TimestampMode::Shift(10).apply(None), None);
+    }
+}
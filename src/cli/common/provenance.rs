@@ -0,0 +1,289 @@
+use std::{collections::BTreeMap, str::FromStr, time::SystemTime};
+
+use clap::Args;
+use thiserror::Error;
+
+use crate::message::Message;
+
+/// Options recording where each copied message came from.
+#[derive(Debug, Default, Args)]
+pub struct ProvenanceOpts {
+    /// Add headers to every copied message recording its original topic,
+    /// partition, offset and timestamp, the source it was copied from, and an
+    /// ID identifying this copy run.
+    ///
+    /// The source is the broker addresses of a Kafka source, or the path of a
+    /// file source. Headers already present in a message are never
+    /// overwritten.
+    #[clap(long)]
+    pub provenance_headers: bool,
+
+    /// Rename a provenance header, in the form "<field>=<header name>".
+    ///
+    /// The fields (and their default header names) are "topic"
+    /// (ktool-source-topic), "partition" (ktool-source-partition), "offset"
+    /// (ktool-source-offset), "timestamp" (ktool-source-timestamp), "source"
+    /// (ktool-source) and "run-id" (ktool-run-id). Can be repeated.
+    #[clap(long, requires = "provenance-headers")]
+    pub provenance_header_name: Vec<HeaderName>,
+
+    /// The ID written to the "run-id" provenance header, instead of one
+    /// generated from the current time and process ID.
+    #[clap(long, requires = "provenance-headers")]
+    pub run_id: Option<String>,
+}
+
+/// A provenance value recorded in a header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ProvenanceField {
+    Topic,
+    Partition,
+    Offset,
+    Timestamp,
+    Source,
+    RunId,
+}
+
+impl ProvenanceField {
+    const ALL: [ProvenanceField; 6] = [
+        ProvenanceField::Topic,
+        ProvenanceField::Partition,
+        ProvenanceField::Offset,
+        ProvenanceField::Timestamp,
+        ProvenanceField::Source,
+        ProvenanceField::RunId,
+    ];
+
+    fn default_header(&self) -> &'static str {
+        match self {
+            ProvenanceField::Topic => "ktool-source-topic",
+            ProvenanceField::Partition => "ktool-source-partition",
+            ProvenanceField::Offset => "ktool-source-offset",
+            ProvenanceField::Timestamp => "ktool-source-timestamp",
+            ProvenanceField::Source => "ktool-source",
+            ProvenanceField::RunId => "ktool-run-id",
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum HeaderNameError {
+    #[error("invalid header name (expected '<field>=<header name>')")]
+    Format,
+
+    #[error(
+        "unknown provenance field '{}' (expected 'topic', 'partition', 'offset', \
+        'timestamp', 'source', or 'run-id')",
+        .0
+    )]
+    Field(String),
+}
+
+/// A user-provided header name for a [`ProvenanceField`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderName {
+    field: ProvenanceField,
+    name: String,
+}
+
+impl FromStr for HeaderName {
+    type Err = HeaderNameError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (field, name) = s
+            .split_once('=')
+            .filter(|(_, name)| !name.is_empty())
+            .ok_or(HeaderNameError::Format)?;
+
+        let field = match field {
+            "topic" => ProvenanceField::Topic,
+            "partition" => ProvenanceField::Partition,
+            "offset" => ProvenanceField::Offset,
+            "timestamp" => ProvenanceField::Timestamp,
+            "source" => ProvenanceField::Source,
+            "run-id" => ProvenanceField::RunId,
+            v => return Err(HeaderNameError::Field(v.to_string())),
+        };
+
+        Ok(Self {
+            field,
+            name: name.to_string(),
+        })
+    }
+}
+
+/// Adds provenance headers to copied messages.
+#[derive(Debug)]
+pub(crate) struct Provenance {
+    names: BTreeMap<ProvenanceField, String>,
+    source: String,
+    run_id: String,
+}
+
+impl Provenance {
+    /// Initialise the provenance headers for messages read from `source`,
+    /// returning [`None`] if they are not enabled.
+    pub(crate) fn new(opts: &ProvenanceOpts, source: String) -> Option<Self> {
+        if !opts.provenance_headers {
+            return None;
+        }
+
+        let mut names = ProvenanceField::ALL
+            .iter()
+            .map(|f| (*f, f.default_header().to_string()))
+            .collect::<BTreeMap<_, _>>();
+        for v in &opts.provenance_header_name {
+            names.insert(v.field, v.name.clone());
+        }
+
+        let run_id = opts.run_id.clone().unwrap_or_else(|| {
+            let now = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .expect("system clock before unix epoch")
+                .as_millis();
+            format!("{:x}-{:x}", now, std::process::id())
+        });
+
+        Some(Self {
+            names,
+            source,
+            run_id,
+        })
+    }
+
+    /// The ID identifying this copy run.
+    pub(crate) fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    /// Add the provenance headers to `msg`, leaving any existing headers with
+    /// the same names untouched.
+    pub(crate) fn stamp(&self, msg: &mut Message) {
+        let values = [
+            (ProvenanceField::Topic, Some(msg.topic().to_string())),
+            (
+                ProvenanceField::Partition,
+                Some(msg.partition().to_string()),
+            ),
+            (ProvenanceField::Offset, Some(msg.offset().to_string())),
+            (
+                ProvenanceField::Timestamp,
+                msg.timestamp().map(|v| v.millis().to_string()),
+            ),
+            (ProvenanceField::Source, Some(self.source.clone())),
+            (ProvenanceField::RunId, Some(self.run_id.clone())),
+        ];
+
+        let headers = msg.headers_mut();
+        for (field, value) in values {
+            if let Some(v) = value {
+                headers
+                    .entry(self.names[&field].clone())
+                    .or_insert_with(|| v.into_bytes());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        cli::common::test_util::test_parse,
+        message::{test_util::message, Timestamp},
+    };
+
+    use super::*;
+
+    test_parse!(
+        topic,
+        type = HeaderName,
+        input = "topic=x-origin-topic",
+        want = Ok(HeaderName { field: ProvenanceField::Topic, name }) if name == "x-origin-topic"
+    );
+
+    test_parse!(
+        run_id,
+        type = HeaderName,
+        input = "run-id=replay",
+        want = Ok(HeaderName {
+            field: ProvenanceField::RunId,
+            ..
+        })
+    );
+
+    test_parse!(
+        unknown_field,
+        type = HeaderName,
+        input = "bananas=x",
+        want = Err(HeaderNameError::Field(_))
+    );
+
+    test_parse!(
+        no_name,
+        type = HeaderName,
+        input = "topic=",
+        want = Err(HeaderNameError::Format)
+    );
+
+    test_parse!(
+        no_equals,
+        type = HeaderName,
+        input = "topic",
+        want = Err(HeaderNameError::Format)
+    );
+
+    #[test]
+    fn test_stamp() {
+        let opts = ProvenanceOpts {
+            provenance_headers: true,
+            provenance_header_name: vec!["offset=x-offset".parse().unwrap()],
+            run_id: Some("run-42".to_string()),
+        };
+
+        let p = Provenance::new(&opts, "broker:9092".to_string()).expect("enabled");
+        assert_eq!(p.run_id(), "run-42");
+
+        let mut msg = message()
+            .partition(3)
+            .offset(42)
+            .timestamp(Timestamp::CreateTime(1234))
+            .header("ktool-source-topic", "upstream")
+            .build();
+
+        p.stamp(&mut msg);
+
+        let want = BTreeMap::from([
+            // An existing header is not overwritten.
+            ("ktool-source-topic".to_string(), b"upstream".to_vec()),
+            ("ktool-source-partition".to_string(), b"3".to_vec()),
+            ("x-offset".to_string(), b"42".to_vec()),
+            ("ktool-source-timestamp".to_string(), b"1234".to_vec()),
+            ("ktool-source".to_string(), b"broker:9092".to_vec()),
+            ("ktool-run-id".to_string(), b"run-42".to_vec()),
+        ]);
+        assert_eq!(msg.headers(), Some(&want));
+    }
+
+    #[test]
+    fn test_stamp_no_timestamp() {
+        let opts = ProvenanceOpts {
+            provenance_headers: true,
+            ..Default::default()
+        };
+
+        let p = Provenance::new(&opts, "./dump.kbin".to_string()).expect("enabled");
+
+        let mut msg = message().build();
+        p.stamp(&mut msg);
+
+        let headers = msg.headers().unwrap();
+        assert!(!headers.contains_key("ktool-source-timestamp"));
+        assert_eq!(headers["ktool-source"], b"./dump.kbin");
+        assert!(!headers["ktool-run-id"].is_empty());
+    }
+
+    #[test]
+    fn test_disabled() {
+        assert!(Provenance::new(&ProvenanceOpts::default(), String::new()).is_none());
+    }
+}
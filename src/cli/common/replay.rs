@@ -0,0 +1,245 @@
+use std::{
+    fmt::Display,
+    num::ParseFloatError,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use clap::Args;
+use thiserror::Error;
+
+use crate::message::Message;
+
+use super::{parse_duration, OffsetError};
+
+/// Options reproducing the original timing of the copied messages.
+#[derive(Debug, Default, Args)]
+pub struct ReplayOpts {
+    /// Write messages with the same gaps between them as the gaps between
+    /// their original timestamps, scaled by a speed factor.
+    ///
+    /// For example, "1x" reproduces the original timing, "10x" replays ten
+    /// times faster, and "0.5x" at half the original speed. Messages without
+    /// a timestamp, or with a timestamp earlier than a previous message, are
+    /// written without delay.
+    #[clap(long)]
+    pub replay_timing: Option<Speed>,
+
+    /// The longest wait between two messages when using --replay-timing, such
+    /// as "5s" or "1m", skipping over any longer idle periods.
+    #[clap(long, requires = "replay-timing", parse(try_from_str = parse_gap))]
+    pub max_gap: Option<Duration>,
+}
+
+fn parse_gap(s: &str) -> Result<Duration, OffsetError> {
+    parse_duration(s).map(|v| Duration::from_millis(v as u64))
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum SpeedError {
+    #[error("invalid replay speed: {}", .0)]
+    ParseFloat(#[from] ParseFloatError),
+
+    #[error("replay speed must be greater than zero")]
+    NotPositive,
+}
+
+/// A replay speed multiplier.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Speed(f64);
+
+impl FromStr for Speed {
+    type Err = SpeedError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let v = s.strip_suffix('x').unwrap_or(s).parse::<f64>()?;
+        if v <= 0.0 || !v.is_finite() {
+            return Err(SpeedError::NotPositive);
+        }
+
+        Ok(Self(v))
+    }
+}
+
+impl Display for Speed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x", self.0)
+    }
+}
+
+/// Delays messages to reproduce the gaps between their original timestamps.
+#[derive(Debug)]
+pub(crate) struct ReplayClock {
+    speed: Speed,
+    max_gap: Option<Duration>,
+
+    /// The time the first timestamped message was written.
+    start: Option<Instant>,
+
+    /// The scheduled write time of the most recent message, relative to
+    /// `start`.
+    elapsed: Duration,
+
+    /// The latest timestamp (milliseconds since epoch) seen so far.
+    last_ts: i64,
+
+    /// The pass over the source the most recent message was read in.
+    ///
+    /// Timestamps restart from the beginning in each pass, so the gap
+    /// between passes is ignored.
+    pass: usize,
+}
+
+impl ReplayClock {
+    /// Initialise a clock for `opts`, returning [`None`] if replay timing is
+    /// not enabled.
+    pub(crate) fn new(opts: &ReplayOpts) -> Option<Self> {
+        Some(Self {
+            speed: opts.replay_timing?,
+            max_gap: opts.max_gap,
+            start: None,
+            elapsed: Duration::ZERO,
+            last_ts: i64::MIN,
+            pass: 0,
+        })
+    }
+
+    /// Block until `msg`, read in the given `pass` over the source, is due to
+    /// be written.
+    pub(crate) fn wait(&mut self, msg: &Message, pass: usize) {
+        let delay = self.delay(msg, pass, Instant::now());
+        if !delay.is_zero() {
+            std::thread::sleep(delay);
+        }
+    }
+
+    /// Schedule `msg`, returning how long to wait from `now` until it is due.
+    fn delay(&mut self, msg: &Message, pass: usize, now: Instant) -> Duration {
+        let ts = match msg.timestamp() {
+            Some(v) => v.millis(),
+            None => return Duration::ZERO,
+        };
+
+        if pass != self.pass {
+            self.pass = pass;
+            self.last_ts = ts;
+        }
+
+        let start = match self.start {
+            Some(v) => v,
+            None => {
+                self.start = Some(now);
+                self.last_ts = ts;
+                return Duration::ZERO;
+            }
+        };
+
+        if ts > self.last_ts {
+            let gap = Duration::from_secs_f64((ts - self.last_ts) as f64 / 1000.0 / self.speed.0);
+            self.elapsed += match self.max_gap {
+                Some(max) => gap.min(max),
+                None => gap,
+            };
+            self.last_ts = ts;
+        }
+
+        (start + self.elapsed).saturating_duration_since(now)
+    }
+}
+
+impl Display for ReplayClock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "replay {}", self.speed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        cli::common::test_util::test_parse,
+        message::{test_util::message, Timestamp},
+    };
+
+    use super::*;
+
+    test_parse!(one, type = Speed, input = "1x", want = Ok(Speed(v)) if v == 1.0);
+    test_parse!(faster, type = Speed, input = "10x", want = Ok(Speed(v)) if v == 10.0);
+    test_parse!(slower, type = Speed, input = "0.5x", want = Ok(Speed(v)) if v == 0.5);
+    test_parse!(no_suffix, type = Speed, input = "2", want = Ok(Speed(v)) if v == 2.0);
+    test_parse!(zero, type = Speed, input = "0x", want = Err(SpeedError::NotPositive));
+    test_parse!(negative, type = Speed, input = "-1x", want = Err(SpeedError::NotPositive));
+    test_parse!(
+        invalid,
+        type = Speed,
+        input = "bananas",
+        want = Err(SpeedError::ParseFloat(_))
+    );
+
+    fn msg(ts: Option<i64>) -> Message {
+        message().timestamp(ts.map(Timestamp::CreateTime)).build()
+    }
+
+    #[test]
+    fn test_delay() {
+        let mut clock = ReplayClock::new(&ReplayOpts {
+            replay_timing: Some(Speed(2.0)),
+            max_gap: Some(Duration::from_secs(5)),
+        })
+        .expect("replay enabled");
+
+        let start = Instant::now();
+
+        // The first message is written immediately.
+        assert_eq!(clock.delay(&msg(Some(10_000)), 0, start), Duration::ZERO);
+
+        // A 1s gap at 2x speed is due 500ms later.
+        assert_eq!(
+            clock.delay(&msg(Some(11_000)), 0, start),
+            Duration::from_millis(500)
+        );
+
+        // Messages without a timestamp, or with an earlier timestamp, are
+        // written without further delay.
+        assert_eq!(clock.delay(&msg(None), 0, start), Duration::ZERO);
+        assert_eq!(
+            clock.delay(&msg(Some(10_500)), 0, start),
+            Duration::from_millis(500)
+        );
+
+        // Time spent writing counts towards the next gap.
+        let later = start + Duration::from_millis(700);
+        assert_eq!(
+            clock.delay(&msg(Some(12_000)), 0, later),
+            Duration::from_millis(300)
+        );
+
+        // An hour long gap is capped.
+        assert_eq!(
+            clock.delay(&msg(Some(3_612_000)), 0, later),
+            Duration::from_millis(5300)
+        );
+
+        // A writer that has fallen behind does not wait.
+        let behind = start + Duration::from_secs(60);
+        assert_eq!(
+            clock.delay(&msg(Some(3_612_100)), 0, behind),
+            Duration::ZERO
+        );
+
+        // The next pass over the source continues from the schedule of the
+        // previous one.
+        assert_eq!(
+            clock.delay(&msg(Some(10_000)), 1, later),
+            Duration::from_millis(5350)
+        );
+        assert_eq!(
+            clock.delay(&msg(Some(11_000)), 1, later),
+            Duration::from_millis(5850)
+        );
+    }
+
+    #[test]
+    fn test_disabled() {
+        assert!(ReplayClock::new(&ReplayOpts::default()).is_none());
+    }
+}
@@ -0,0 +1,315 @@
+use std::{cell::OnceCell, num::ParseIntError, str::FromStr};
+
+use regex::Regex;
+use thiserror::Error;
+
+use crate::message::Message;
+
+use super::{Target, TargetError};
+
+#[derive(Debug, Error)]
+pub enum RouteError {
+    #[error("invalid route format (expected '<condition> => <target>')")]
+    Format,
+
+    #[error(
+        "invalid route condition (expected 'header:<name>=<value>', 'key:<regex>', \
+        'partition:<n>', or 'field:<path>=<value>')"
+    )]
+    Condition,
+
+    #[error("invalid route partition: {}", .0)]
+    Partition(#[from] ParseIntError),
+
+    #[error("invalid route key pattern: {}", .0)]
+    Pattern(#[from] regex::Error),
+
+    #[error("invalid route target: {}", .0)]
+    Target(#[from] TargetError),
+}
+
+/// A rule sending the messages matching a [`RouteCondition`] to a [`Target`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+    pub condition: RouteCondition,
+    pub target: Target,
+}
+
+impl FromStr for Route {
+    type Err = RouteError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Targets never contain "=>", so split on the last one to allow it in
+        // condition values.
+        let (condition, target) = s.rsplit_once("=>").ok_or(RouteError::Format)?;
+
+        Ok(Self {
+            condition: condition.trim().parse()?,
+            target: target.trim().parse()?,
+        })
+    }
+}
+
+/// A predicate selecting messages by their content.
+#[derive(Debug, Clone)]
+pub enum RouteCondition {
+    /// The message has a header with this name and (exact) value.
+    Header { name: String, value: Vec<u8> },
+
+    /// The message key (lossily decoded as UTF-8) matches this regex.
+    Key(Regex),
+
+    /// The message was read from this source partition.
+    Partition(i32),
+
+    /// The message payload is a JSON object, with the field at this
+    /// dot-separated path equal to the value.
+    ///
+    /// String fields are compared to the value as-is, and all other types to
+    /// the value parsed as JSON.
+    Field { path: Vec<String>, value: String },
+}
+
+impl RouteCondition {
+    /// Returns true if `msg` satisfies this condition, given the lazily
+    /// decoded JSON `payload` of the message.
+    pub fn matches(&self, msg: &Message, payload: &Payload<'_>) -> bool {
+        match self {
+            RouteCondition::Header { name, value } => msg
+                .headers()
+                .and_then(|h| h.get(name))
+                .is_some_and(|v| v == value),
+            RouteCondition::Key(re) => msg
+                .key()
+                .is_some_and(|v| re.is_match(&String::from_utf8_lossy(v))),
+            RouteCondition::Partition(v) => msg.partition() == *v,
+            RouteCondition::Field { path, value } => {
+                let field = payload
+                    .json()
+                    .and_then(|v| path.iter().try_fold(v, |acc, name| acc.get(name)));
+
+                match field {
+                    Some(serde_json::Value::String(v)) => v == value,
+                    Some(v) => serde_json::from_str::<serde_json::Value>(value)
+                        .is_ok_and(|want| want == *v),
+                    None => false,
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for RouteCondition {
+    type Err = RouteError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, spec) = s.split_once(':').ok_or(RouteError::Condition)?;
+
+        Ok(match kind {
+            "header" => {
+                let (name, value) = spec.split_once('=').ok_or(RouteError::Condition)?;
+                RouteCondition::Header {
+                    name: name.to_string(),
+                    value: value.as_bytes().to_vec(),
+                }
+            }
+            "key" => RouteCondition::Key(Regex::new(spec)?),
+            "partition" => RouteCondition::Partition(spec.parse()?),
+            "field" => {
+                let (path, value) = spec.split_once('=').ok_or(RouteError::Condition)?;
+                RouteCondition::Field {
+                    path: path.split('.').map(ToString::to_string).collect(),
+                    value: value.to_string(),
+                }
+            }
+            _ => return Err(RouteError::Condition),
+        })
+    }
+}
+
+impl PartialEq for RouteCondition {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Self::Header { name, value },
+                Self::Header {
+                    name: n2,
+                    value: v2,
+                },
+            ) => name == n2 && value == v2,
+            (Self::Key(a), Self::Key(b)) => a.as_str() == b.as_str(),
+            (Self::Partition(a), Self::Partition(b)) => a == b,
+            (
+                Self::Field { path, value },
+                Self::Field {
+                    path: p2,
+                    value: v2,
+                },
+            ) => path == p2 && value == v2,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for RouteCondition {}
+
+/// The payload of a message, decoded as JSON at most once, and only if a
+/// condition inspects it.
+pub struct Payload<'a> {
+    raw: Option<&'a [u8]>,
+    json: OnceCell<Option<serde_json::Value>>,
+}
+
+impl<'a> Payload<'a> {
+    pub fn new(msg: &'a Message) -> Self {
+        Self {
+            raw: msg.payload(),
+            json: OnceCell::new(),
+        }
+    }
+
+    fn json(&self) -> Option<&serde_json::Value> {
+        self.json
+            .get_or_init(|| self.raw.and_then(|v| serde_json::from_slice(v).ok()))
+            .as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{cli::common::test_util::test_parse, message::test_util::message};
+
+    use super::*;
+
+    test_parse!(
+        header,
+        type = Route,
+        input = "header:type=order => kafka://127.0.0.1/orders",
+        want = Ok(Route {
+            condition: RouteCondition::Header { name, value },
+            target: Target::Kafka { topic, .. },
+        }) if name == "type" && value == b"order" && topic == "orders"
+    );
+
+    test_parse!(
+        key,
+        type = Route,
+        input = "key:^eu-=>./eu.kbin",
+        want = Ok(Route {
+            condition: RouteCondition::Key(re),
+            target: Target::Path(_),
+        }) if re.as_str() == "^eu-"
+    );
+
+    test_parse!(
+        partition,
+        type = Route,
+        input = "partition:3 => ./p3.kbin",
+        want = Ok(Route {
+            condition: RouteCondition::Partition(3),
+            ..
+        })
+    );
+
+    test_parse!(
+        field,
+        type = Route,
+        input = "field:event.type=created => ./created.kbin",
+        want = Ok(Route {
+            condition: RouteCondition::Field { path, value },
+            ..
+        }) if path == ["event", "type"] && value == "created"
+    );
+
+    test_parse!(
+        arrow_in_value,
+        type = Route,
+        input = "header:rule=a=>b => ./arrow.kbin",
+        want = Ok(Route {
+            condition: RouteCondition::Header { name, value },
+            target: Target::Path(_),
+        }) if name == "rule" && value == b"a=>b"
+    );
+
+    test_parse!(
+        no_target,
+        type = Route,
+        input = "partition:3",
+        want = Err(RouteError::Format)
+    );
+
+    test_parse!(
+        bad_condition,
+        type = Route,
+        input = "bananas => ./p3.kbin",
+        want = Err(RouteError::Condition)
+    );
+
+    test_parse!(
+        bad_kind,
+        type = Route,
+        input = "bananas:1 => ./p3.kbin",
+        want = Err(RouteError::Condition)
+    );
+
+    test_parse!(
+        bad_partition,
+        type = Route,
+        input = "partition:x => ./p3.kbin",
+        want = Err(RouteError::Partition(_))
+    );
+
+    test_parse!(
+        bad_regex,
+        type = Route,
+        input = "key:( => ./p3.kbin",
+        want = Err(RouteError::Pattern(_))
+    );
+
+    test_parse!(
+        bad_target,
+        type = Route,
+        input = "partition:1 => kafka://broker",
+        want = Err(RouteError::Target(_))
+    );
+
+    fn matches(condition: &str, msg: &Message) -> bool {
+        let c = condition.parse::<RouteCondition>().unwrap();
+        c.matches(msg, &Payload::new(msg))
+    }
+
+    #[test]
+    fn test_matches() {
+        let msg = message()
+            .partition(2)
+            .header("type", "order")
+            .key(b"eu-42".to_vec())
+            .payload(r#"{"event":{"type":"created","version":2}}"#)
+            .build();
+
+        assert!(matches("header:type=order", &msg));
+        assert!(!matches("header:type=refund", &msg));
+        assert!(!matches("header:other=order", &msg));
+
+        assert!(matches("key:^eu-", &msg));
+        assert!(!matches("key:^us-", &msg));
+
+        assert!(matches("partition:2", &msg));
+        assert!(!matches("partition:1", &msg));
+
+        assert!(matches("field:event.type=created", &msg));
+        assert!(matches("field:event.version=2", &msg));
+        assert!(!matches("field:event.type=deleted", &msg));
+        assert!(!matches("field:event.missing=created", &msg));
+        assert!(!matches("field:event.type.nested=created", &msg));
+    }
+
+    #[test]
+    fn test_matches_empty() {
+        let msg = message().payload("not json").build();
+
+        assert!(!matches("header:type=order", &msg));
+        assert!(!matches("key:.*", &msg));
+        assert!(!matches("field:type=order", &msg));
+    }
+}
@@ -0,0 +1,39 @@
+/// Render `rows` (the first being the header) as a table of left-aligned
+/// columns separated by two spaces.
+///
+/// All rows must have the same number of cells.
+pub fn render_table(rows: &[Vec<String>]) -> String {
+    let n = rows.first().map(Vec::len).unwrap_or_default();
+    let widths = (0..n)
+        .map(|i| rows.iter().map(|r| r[i].len()).max().unwrap_or_default())
+        .collect::<Vec<_>>();
+
+    let mut out = String::new();
+    for row in rows {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(v, w)| format!("{:<w$}", v, w = w))
+            .collect::<Vec<_>>()
+            .join("  ");
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_table() {
+        let rows = [
+            vec!["NAME".to_string(), "N".to_string(), "NOTE".to_string()],
+            vec!["bananas".to_string(), "42".to_string(), String::new()],
+        ];
+
+        assert_eq!(render_table(&rows), "NAME     N   NOTE\nbananas  42\n");
+        assert_eq!(render_table(&[]), "");
+    }
+}
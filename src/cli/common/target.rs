@@ -1,5 +1,6 @@
-use std::{num::ParseIntError, path::PathBuf, str::FromStr};
+use std::{fmt::Display, num::ParseIntError, path::PathBuf, str::FromStr};
 
+use regex::Regex;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -8,19 +9,88 @@ pub enum TargetError {
     ParseInt(#[from] ParseIntError),
 
     #[error(
-        "invalid target format (expected 'path', or 'kafka://brokers/topic/<optional-partition>')"
+        "invalid target format (expected 'path', '-', 'ndjson:<path or ->', or \
+        'kafka://brokers/topic/<optional-partition>')"
     )]
     Invalid,
+
+    #[error("invalid topic pattern: {}", .0)]
+    Pattern(#[from] regex::Error),
+
+    #[error("a partition cannot be specified for a topic pattern")]
+    PatternPartition,
+}
+
+/// A pattern selecting topics by name.
+///
+/// Patterns are either a regular expression prefixed with '~' (such as
+/// "~orders\..*"), or a glob containing '*' or '?' wildcards (such as
+/// "orders.*"). Both must match the entire topic name.
+#[derive(Debug, Clone)]
+pub struct TopicPattern(Regex);
+
+impl TopicPattern {
+    /// Returns true if `topic` is selected by this pattern.
+    pub fn is_match(&self, topic: &str) -> bool {
+        self.0.is_match(topic)
+    }
+
+    /// Parse `s` as a topic pattern, returning [`None`] if it is a literal
+    /// topic name.
+    fn parse(s: &str) -> Result<Option<Self>, TargetError> {
+        let expr = if let Some(v) = s.strip_prefix('~') {
+            v.to_string()
+        } else if s.contains(['*', '?']) {
+            s.split_inclusive(['*', '?'])
+                .map(|v| match v.strip_suffix('*') {
+                    Some(v) => format!("{}.*", regex::escape(v)),
+                    None => match v.strip_suffix('?') {
+                        Some(v) => format!("{}.", regex::escape(v)),
+                        None => regex::escape(v),
+                    },
+                })
+                .collect()
+        } else {
+            return Ok(None);
+        };
+
+        Ok(Some(Self(Regex::new(&format!("^(?:{})$", expr))?)))
+    }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+impl PartialEq for TopicPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+impl Eq for TopicPattern {}
+
+impl Display for TopicPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.as_str().fmt(f)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Target {
     Kafka {
         brokers: Vec<String>,
         topic: String,
         partition: Option<i32>,
     },
+    KafkaPattern {
+        brokers: Vec<String>,
+        pattern: TopicPattern,
+    },
     Path(PathBuf),
+
+    /// A dump streamed through stdin when reading, or stdout when writing.
+    Stdio,
+
+    /// Newline delimited JSON messages in a file, or stdin/stdout if
+    /// [`None`].
+    Ndjson(Option<PathBuf>),
 }
 
 impl FromStr for Target {
@@ -37,12 +107,23 @@ impl FromStr for Target {
 
             let target = match *parts.as_slice() {
                 // Never accept empty broker strings, or empty topics
-                [brokers, topic] if !brokers.is_empty() && !topic.is_empty() => Self::Kafka {
-                    brokers: brokers.split(',').map(ToString::to_string).collect(),
-                    topic: topic.to_string(),
-                    partition: None,
-                },
+                [brokers, topic] if !brokers.is_empty() && !topic.is_empty() => {
+                    match TopicPattern::parse(topic)? {
+                        Some(pattern) => Self::KafkaPattern {
+                            brokers: brokers.split(',').map(ToString::to_string).collect(),
+                            pattern,
+                        },
+                        None => Self::Kafka {
+                            brokers: brokers.split(',').map(ToString::to_string).collect(),
+                            topic: topic.to_string(),
+                            partition: None,
+                        },
+                    }
+                }
                 [brokers, topic, partition] if !brokers.is_empty() && !topic.is_empty() => {
+                    if TopicPattern::parse(topic)?.is_some() {
+                        return Err(TargetError::PatternPartition);
+                    }
                     Self::Kafka {
                         brokers: brokers.split(',').map(ToString::to_string).collect(),
                         topic: topic.to_string(),
@@ -55,10 +136,51 @@ impl FromStr for Target {
             return Ok(target);
         }
 
+        if let Some(path) = s.strip_prefix("ndjson:") {
+            return match path {
+                "" => Err(TargetError::Invalid),
+                "-" => Ok(Self::Ndjson(None)),
+                v => Ok(Self::Ndjson(Some(v.into()))),
+            };
+        }
+
+        if s == "-" {
+            return Ok(Self::Stdio);
+        }
+
         Ok(Self::Path(s.into()))
     }
 }
 
+impl Target {
+    /// Returns true if this target reads from stdin, or writes to stdout.
+    pub fn is_stdio(&self) -> bool {
+        matches!(self, Target::Stdio | Target::Ndjson(None))
+    }
+}
+
+impl Display for Target {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Target::Kafka {
+                brokers,
+                topic,
+                partition: Some(p),
+            } => write!(f, "kafka://{}/{}/{}", brokers.join(","), topic, p),
+            Target::Kafka { brokers, topic, .. } => {
+                write!(f, "kafka://{}/{}", brokers.join(","), topic)
+            }
+            Target::KafkaPattern { brokers, pattern } => {
+                write!(f, "kafka://{}/~{}", brokers.join(","), pattern)
+            }
+            Target::Path(v) => v.display().fmt(f),
+            Target::Stdio => write!(f, "-"),
+            Target::Ndjson(Some(v)) => write!(f, "ndjson:{}", v.display()),
+            Target::Ndjson(None) => write!(f, "ndjson:-"),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -239,6 +361,62 @@ mod tests {
         }
     );
 
+    test_parse!(
+        kafka_regex,
+        input = "kafka://localhost:9092/~orders\\..*",
+        want = Ok(Target::KafkaPattern{brokers, pattern}) => {
+            assert_eq!(brokers, ["localhost:9092"]);
+            assert!(pattern.is_match("orders.eu"));
+            assert!(pattern.is_match("orders.us.v2"));
+            assert!(!pattern.is_match("orders"));
+            assert!(!pattern.is_match("ordersXeu"));
+            assert!(!pattern.is_match("legacy.orders.eu"));
+        }
+    );
+
+    test_parse!(
+        kafka_glob,
+        input = "kafka://localhost:9092/orders.*",
+        want = Ok(Target::KafkaPattern{brokers, pattern}) => {
+            assert_eq!(brokers, ["localhost:9092"]);
+            assert!(pattern.is_match("orders.eu"));
+            assert!(pattern.is_match("orders."));
+            assert!(!pattern.is_match("ordersXeu"));
+            assert!(!pattern.is_match("legacy.orders.eu"));
+        }
+    );
+
+    test_parse!(
+        kafka_glob_single_char,
+        input = "kafka://localhost:9092/orders-v?",
+        want = Ok(Target::KafkaPattern{pattern, ..}) => {
+            assert!(pattern.is_match("orders-v1"));
+            assert!(pattern.is_match("orders-v2"));
+            assert!(!pattern.is_match("orders-v10"));
+        }
+    );
+
+    test_parse!(
+        kafka_glob_trailing_slash,
+        input = "kafka://localhost:9092/*/",
+        want = Ok(Target::KafkaPattern{pattern, ..}) => {
+            assert!(pattern.is_match("bananas"));
+            assert!(pattern.is_match("__consumer_offsets"));
+        }
+    );
+
+    test_parse!(
+        kafka_regex_invalid,
+        input = "kafka://localhost:9092/~orders(",
+        want = Err(TargetError::Pattern(_))
+    );
+
+    test_parse!(
+        kafka_pattern_partition,
+        input = "kafka://localhost:9092/orders.*/4",
+        want = Err(TargetError::PatternPartition)
+    );
+
     test_parse!(
         relative_path,
         input = "data.bin",
@@ -254,4 +432,40 @@ mod tests {
             assert_eq!(p.to_str(), Some("/test/data.bin"));
         }
     );
+
+    test_parse!(stdio, input = "-", want = Ok(Target::Stdio));
+
+    test_parse!(
+        ndjson,
+        input = "ndjson:./export.jsonl",
+        want = Ok(Target::Ndjson(Some(p))) => {
+            assert_eq!(p.to_str(), Some("./export.jsonl"));
+        }
+    );
+
+    test_parse!(
+        ndjson_stdio,
+        input = "ndjson:-",
+        want = Ok(Target::Ndjson(None))
+    );
+
+    test_parse!(
+        ndjson_empty,
+        input = "ndjson:",
+        want = Err(TargetError::Invalid)
+    );
+
+    #[test]
+    fn test_display() {
+        for input in [
+            "kafka://127.0.0.1:9092,another:9092/my_topic/0",
+            "kafka://127.0.0.1/my_topic",
+            "./dump.kbin",
+            "ndjson:./export.jsonl",
+            "ndjson:-",
+            "-",
+        ] {
+            assert_eq!(input.parse::<Target>().unwrap().to_string(), input);
+        }
+    }
 }
@@ -0,0 +1,20 @@
+/// Generate a test asserting that parsing `input` as `type` matches the `want`
+/// pattern, named after the type and `$name`.
+macro_rules! test_parse {
+    (
+        $name:ident,
+        type = $type:ident,
+        input = $input:literal,
+        want = $($want:tt)+
+    ) => {
+        paste::paste! {
+            #[test]
+            fn [<test_parse_ $type:snake _ $name>]() {
+                let input: &str = $input;
+                assert_matches::assert_matches!(input.parse::<$type>(), $($want)+);
+            }
+        }
+    };
+}
+
+pub(crate) use test_parse;
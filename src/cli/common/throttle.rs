@@ -0,0 +1,406 @@
+use std::{
+    fmt::Display,
+    num::ParseFloatError,
+    str::FromStr,
+    sync::atomic::{AtomicI32, Ordering},
+    time::{Duration, Instant},
+};
+
+use clap::Args;
+use indicatif::HumanBytes;
+use thiserror::Error;
+
+use crate::message::Message;
+
+/// The largest factor of two a limit can be scaled up or down by at runtime.
+const MAX_SCALE: i32 = 16;
+
+/// The number of times the limits have been doubled (positive) or halved
+/// (negative) by a signal.
+static SCALE: AtomicI32 = AtomicI32::new(0);
+
+/// Options limiting the throughput of a copy.
+#[derive(Debug, Default, Args)]
+pub struct ThrottleOpts {
+    /// Limit the number of messages written per second, such as "5000/s" or
+    /// "600/m".
+    ///
+    /// On unix systems the limits can be adjusted while running - SIGUSR1
+    /// doubles them, and SIGUSR2 halves them.
+    #[clap(long)]
+    pub rate: Option<Rate>,
+
+    /// Limit the number of bytes written per second, such as "20MiB/s" or
+    /// "500KB/s".
+    ///
+    /// Message size is the combined size of the key, payload and headers.
+    #[clap(long)]
+    pub bandwidth: Option<Bandwidth>,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ThrottleError {
+    #[error("invalid limit: {}", .0)]
+    ParseFloat(#[from] ParseFloatError),
+
+    #[error("limit must be greater than zero")]
+    NotPositive,
+
+    #[error("invalid limit period '{}' (expected 's', 'm' or 'h')", .0)]
+    Period(String),
+
+    #[error(
+        "invalid unit '{}' (expected 'B', 'KB', 'KiB', 'MB', 'MiB', 'GB', or 'GiB')",
+        .0
+    )]
+    Unit(String),
+}
+
+/// A message rate, in messages per second.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rate(f64);
+
+impl FromStr for Rate {
+    type Err = ThrottleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_limit(s, |unit| match unit {
+            "" => Ok(1.0),
+            v => Err(ThrottleError::Unit(v.to_string())),
+        })
+        .map(Rate)
+    }
+}
+
+impl Display for Rate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:.0} msg/s", self.0)
+    }
+}
+
+/// A data rate, in bytes per second.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bandwidth(f64);
+
+impl FromStr for Bandwidth {
+    type Err = ThrottleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_limit(s, |unit| match unit {
+            "B" => Ok(1.0),
+            "KB" => Ok(1e3),
+            "KiB" => Ok(1024.0),
+            "MB" => Ok(1e6),
+            "MiB" => Ok(1024.0 * 1024.0),
+            "GB" => Ok(1e9),
+            "GiB" => Ok(1024.0 * 1024.0 * 1024.0),
+            v => Err(ThrottleError::Unit(v.to_string())),
+        })
+        .map(Bandwidth)
+    }
+}
+
+impl Display for Bandwidth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/s", HumanBytes(self.0 as u64))
+    }
+}
+
+/// Parse a "<n><unit>/<period>" limit into a per-second value, resolving the
+/// unit multiplier with `unit`.
+///
+/// The period defaults to seconds if not specified.
+fn parse_limit<F>(s: &str, unit: F) -> Result<f64, ThrottleError>
+where
+    F: FnOnce(&str) -> Result<f64, ThrottleError>,
+{
+    let (quantity, period) = s.split_once('/').unwrap_or((s, "s"));
+
+    let period = match period {
+        "s" => 1.0,
+        "m" => 60.0,
+        "h" => 60.0 * 60.0,
+        v => return Err(ThrottleError::Period(v.to_string())),
+    };
+
+    let split = quantity
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(quantity.len());
+    let (n, suffix) = quantity.split_at(split);
+
+    let v = n.parse::<f64>()? * unit(suffix)? / period;
+    if v <= 0.0 {
+        return Err(ThrottleError::NotPositive);
+    }
+
+    Ok(v)
+}
+
+/// Enforces the [`ThrottleOpts`] limits for a stream of messages.
+#[derive(Debug)]
+pub(crate) struct Throttle {
+    rate: Option<(Rate, Limiter)>,
+    bandwidth: Option<(Bandwidth, Limiter)>,
+
+    /// The signal adjustment currently applied to the limits.
+    scale: i32,
+}
+
+impl Throttle {
+    /// Initialise a throttle enforcing `opts`, returning [`None`] if there
+    /// are no limits to enforce.
+    pub(crate) fn new(opts: &ThrottleOpts) -> Option<Self> {
+        if opts.rate.is_none() && opts.bandwidth.is_none() {
+            return None;
+        }
+
+        install_signal_handlers();
+
+        let now = Instant::now();
+        let mut t = Self {
+            rate: opts.rate.map(|v| (v, Limiter::new(v.0, now))),
+            bandwidth: opts.bandwidth.map(|v| (v, Limiter::new(v.0, now))),
+            scale: 0,
+        };
+        t.rescale(SCALE.load(Ordering::Relaxed));
+
+        Some(t)
+    }
+
+    /// Block until `msg` can be written without exceeding the limits,
+    /// returning true if the limits have been adjusted since the last call.
+    pub(crate) fn wait(&mut self, msg: &Message) -> bool {
+        let scale = SCALE.load(Ordering::Relaxed);
+        let adjusted = scale != self.scale;
+        if adjusted {
+            self.rescale(scale);
+        }
+
+        let now = Instant::now();
+        let delay = [
+            self.rate.as_mut().map(|(_, l)| l.delay(1.0, now)),
+            self.bandwidth
+                .as_mut()
+                .map(|(_, l)| l.delay(msg.size() as f64, now)),
+        ]
+        .into_iter()
+        .flatten()
+        .max()
+        .unwrap_or_default();
+
+        if !delay.is_zero() {
+            std::thread::sleep(delay);
+        }
+
+        adjusted
+    }
+
+    fn rescale(&mut self, scale: i32) {
+        self.scale = scale.clamp(-MAX_SCALE, MAX_SCALE);
+        let factor = 2f64.powi(self.scale);
+
+        if let Some((v, l)) = &mut self.rate {
+            l.set_limit(v.0 * factor);
+        }
+        if let Some((v, l)) = &mut self.bandwidth {
+            l.set_limit(v.0 * factor);
+        }
+    }
+}
+
+impl Display for Throttle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let factor = 2f64.powi(self.scale);
+        let limits = [
+            self.rate.map(|(v, _)| Rate(v.0 * factor).to_string()),
+            self.bandwidth
+                .map(|(v, _)| Bandwidth(v.0 * factor).to_string()),
+        ];
+
+        write!(
+            f,
+            "limit {}",
+            limits.into_iter().flatten().collect::<Vec<_>>().join(", ")
+        )
+    }
+}
+
+/// A token bucket permitting `limit` units per second, with bursts of up to
+/// one second worth of units.
+#[derive(Debug, Clone, Copy)]
+struct Limiter {
+    limit: f64,
+    allowance: f64,
+    last: Instant,
+}
+
+impl Limiter {
+    fn new(limit: f64, now: Instant) -> Self {
+        Self {
+            limit,
+            allowance: limit,
+            last: now,
+        }
+    }
+
+    fn set_limit(&mut self, limit: f64) {
+        self.limit = limit;
+        self.allowance = self.allowance.min(limit);
+    }
+
+    /// Consume `n` units at `now`, returning how long to wait before they
+    /// are within the limit.
+    fn delay(&mut self, n: f64, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.last = now;
+
+        self.allowance = (self.allowance + elapsed * self.limit).min(self.limit) - n;
+        if self.allowance >= 0.0 {
+            return Duration::ZERO;
+        }
+
+        Duration::from_secs_f64(-self.allowance / self.limit)
+    }
+}
+
+#[cfg(unix)]
+fn install_signal_handlers() {
+    extern "C" fn handle(sig: libc::c_int) {
+        if sig == libc::SIGUSR1 {
+            SCALE.fetch_add(1, Ordering::Relaxed);
+        } else {
+            SCALE.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    // SAFETY: the handler only performs atomic operations, which are
+    // async-signal-safe.
+    let handler: extern "C" fn(libc::c_int) = handle;
+    unsafe {
+        libc::signal(libc::SIGUSR1, handler as libc::sighandler_t);
+        libc::signal(libc::SIGUSR2, handler as libc::sighandler_t);
+    }
+}
+
+#[cfg(not(unix))]
+fn install_signal_handlers() {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::cli::common::test_util::test_parse;
+
+    test_parse!(per_second, type = Rate, input = "5000/s", want = Ok(Rate(v)) if v == 5000.0);
+    test_parse!(no_period, type = Rate, input = "42", want = Ok(Rate(v)) if v == 42.0);
+    test_parse!(minute, type = Rate, input = "600/m", want = Ok(Rate(v)) if v == 10.0);
+    test_parse!(hour, type = Rate, input = "7200/h", want = Ok(Rate(v)) if v == 2.0);
+    test_parse!(fraction, type = Rate, input = "0.5/s", want = Ok(Rate(v)) if v == 0.5);
+
+    test_parse!(
+        unit,
+        type = Rate,
+        input = "5MiB/s",
+        want = Err(ThrottleError::Unit(_))
+    );
+
+    test_parse!(
+        period,
+        type = Rate,
+        input = "5/d",
+        want = Err(ThrottleError::Period(_))
+    );
+
+    test_parse!(
+        zero,
+        type = Rate,
+        input = "0/s",
+        want = Err(ThrottleError::NotPositive)
+    );
+
+    test_parse!(
+        invalid,
+        type = Rate,
+        input = "bananas",
+        want = Err(ThrottleError::ParseFloat(_))
+    );
+
+    test_parse!(
+        mib,
+        type = Bandwidth,
+        input = "20MiB/s",
+        want = Ok(Bandwidth(v)) if v == 20.0 * 1024.0 * 1024.0
+    );
+
+    test_parse!(
+        kb,
+        type = Bandwidth,
+        input = "500KB/s",
+        want = Ok(Bandwidth(v)) if v == 500_000.0
+    );
+
+    test_parse!(
+        bytes_minute,
+        type = Bandwidth,
+        input = "120B/m",
+        want = Ok(Bandwidth(v)) if v == 2.0
+    );
+
+    test_parse!(
+        no_unit,
+        type = Bandwidth,
+        input = "500/s",
+        want = Err(ThrottleError::Unit(_))
+    );
+
+    test_parse!(
+        bad_unit,
+        type = Bandwidth,
+        input = "500Mb/s",
+        want = Err(ThrottleError::Unit(_))
+    );
+
+    #[test]
+    fn test_limiter() {
+        let start = Instant::now();
+        let mut l = Limiter::new(10.0, start);
+
+        // A burst of up to one second of units is allowed immediately.
+        for _ in 0..10 {
+            assert_eq!(l.delay(1.0, start), Duration::ZERO);
+        }
+
+        // After which units are spaced out.
+        assert_eq!(l.delay(1.0, start), Duration::from_millis(100));
+        assert_eq!(l.delay(1.0, start), Duration::from_millis(200));
+
+        // Waiting replenishes the allowance.
+        let later = start + Duration::from_millis(300);
+        assert_eq!(l.delay(1.0, later), Duration::ZERO);
+
+        // An idle period never builds up more than a second of allowance.
+        let idle = later + Duration::from_secs(60);
+        assert_eq!(l.delay(10.0, idle), Duration::ZERO);
+        assert_eq!(l.delay(5.0, idle), Duration::from_millis(500));
+
+        // Lowering the limit slows subsequent writes.
+        l.set_limit(1.0);
+        assert_eq!(l.delay(1.0, idle), Duration::from_millis(6000));
+    }
+
+    #[test]
+    fn test_throttle_display() {
+        let opts = ThrottleOpts {
+            rate: Some(Rate(5000.0)),
+            bandwidth: Some(Bandwidth(20.0 * 1024.0 * 1024.0)),
+        };
+
+        let mut t = Throttle::new(&opts).expect("limits are set");
+        assert_eq!(t.to_string(), "limit 5000 msg/s, 20.00MiB/s");
+
+        t.rescale(-1);
+        assert_eq!(t.to_string(), "limit 2500 msg/s, 10.00MiB/s");
+
+        assert!(Throttle::new(&ThrottleOpts::default()).is_none());
+    }
+}
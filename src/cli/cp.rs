@@ -1,12 +1,21 @@
-use std::time::Duration;
+use std::{collections::BTreeMap, time::Duration};
 
 use anyhow::{anyhow, Context};
 use clap::Args;
 use indicatif::{HumanDuration, ProgressBar, ProgressStyle};
 
-use crate::{sink, source};
+use crate::{
+    sink::{
+        self,
+        router::Router,
+        tee::{Tee, TeePolicy},
+        FatalError, Sink,
+    },
+    source::{self, StartPosition},
+    topic_config::TopicSpec,
+};
 
-use super::common::{OffsetClap, Target};
+use super::common::{Amplifier, OffsetClap, Provenance, ReplayClock, Route, Target, Throttle};
 
 // TODO(dom): examples
 
@@ -21,61 +30,300 @@ pub struct CliArgs {
     ///
     ///   - File: /path/to/file.bin
     ///
+    ///   - Stdin: "-", reading a dump file streamed through a pipe
+    ///
+    ///   - NDJSON: "ndjson:/path/to/file.jsonl", or "ndjson:-" for stdin
+    ///
     /// Where a Kafka source can specify one or more comma-delimited broker
     /// addresses, a topic, and a optional partition number. Example:
     /// "kafka://127.0.0.1:9092,another:9092/my_topic/0".
     ///
+    /// Instead of a single topic, all partitions of every topic matching a
+    /// glob ("kafka://brokers/orders.*") or a regex prefixed with '~'
+    /// ("kafka://brokers/~orders\..*") can be read at once.
+    ///
     /// Where a file source can be a absolute, or relative file path.
+    ///
+    /// An NDJSON source contains one JSON message per line, in the format
+    /// output by "read --json".
     from: Target,
 
-    /// A message sink specified in the same format as the message source.
-    to: Target,
+    /// One or more message sinks, specified in the same format as the message
+    /// source.
+    ///
+    /// If the sink is an existing directory, messages are written to one file
+    /// per topic, named "<topic>.kbin". A sink of "-" streams the dump to
+    /// stdout, and an NDJSON sink of "ndjson:-" writes to stdout.
+    ///
+    /// When more than one sink is given, every message is written to all of
+    /// them, each with its own --buffer of messages.
+    #[clap(required = true)]
+    to: Vec<Target>,
+
+    /// The behaviour when writing to one of several sinks fails.
+    ///
+    /// With "fail-all" the copy stops, and with "continue" the remaining sinks
+    /// continue to be written to. In both cases the copy exits with an error
+    /// once complete.
+    #[clap(long, arg_enum, default_value = "fail-all")]
+    tee_policy: TeePolicy,
+
+    /// Send the messages matching a condition to a different destination, in
+    /// the form "<condition> => <target>". Can be repeated.
+    ///
+    /// Conditions are checked in order, and each message is written to the
+    /// target of the first matching route, or the sink above if none match.
+    /// Supported conditions are:
+    ///
+    ///   - "header:<name>=<value>": a header with exactly this value
+    ///
+    ///   - "key:<regex>": a key matching the regular expression
+    ///
+    ///   - "partition:<n>": read from source partition n
+    ///
+    ///   - "field:<path>=<value>": a JSON payload with the field at the
+    ///     dot-separated path equal to the value, such as "field:event.type=created"
+    ///
+    /// Targets are specified in the same format as the sink, following the
+    /// last "=>" so conditions can contain it.
+    #[clap(long)]
+    route: Vec<Route>,
 
     /// Maximum number of messages to buffer while writing is blocked.
     #[clap(long, default_value = "100")]
     buffer: usize,
 
+    /// Resume from the offsets committed by the consumer group (--group), and
+    /// commit the copied offsets once all writes have been flushed.
+    ///
+    /// Repeated runs copy only the messages produced since the previous run,
+    /// making this suitable for incremental backups. Offsets are committed
+    /// only after the sink has successfully flushed, so a failed copy never
+    /// skips messages, though it may copy some of them again on the next run.
+    ///
+    /// Requires a Kafka source, and cannot be combined with --offset or
+    /// --time-range.
+    #[clap(long, conflicts_with_all = &["offset", "time-range"])]
+    commit: bool,
+
+    /// Create each destination Kafka topic (including --route targets) if it
+    /// does not already exist, mirroring the partition count, replication factor and topic-level
+    /// configuration (such as "cleanup.policy" or "retention.ms") of the
+    /// source topic.
+    ///
+    /// For a file source, the topic configuration saved alongside the file
+    /// ("<file>.topic.json") when it was copied from Kafka is used.
+    #[clap(long)]
+    create_topic: bool,
+
+    /// The partition count of a topic created with --create-topic, instead of
+    /// that of the source topic.
+    #[clap(long, requires = "create-topic")]
+    partitions: Option<i32>,
+
+    /// The replication factor of a topic created with --create-topic, instead
+    /// of that of the source topic.
+    #[clap(long, requires = "create-topic")]
+    replication_factor: Option<i32>,
+
     #[clap(flatten)]
     offset: OffsetClap,
 
+    #[clap(flatten)]
+    provenance_args: crate::cli::common::ProvenanceOpts,
+
+    #[clap(flatten)]
+    amplify_args: crate::cli::common::AmplifyOpts,
+
+    #[clap(flatten)]
+    replay_args: crate::cli::common::ReplayOpts,
+
+    #[clap(flatten)]
+    throttle_args: crate::cli::common::ThrottleOpts,
+
+    #[clap(flatten)]
+    produce_args: crate::cli::common::ProduceOpts,
+
     #[clap(flatten)]
     kafka_args: crate::cli::common::KafkaOpts,
 }
 
 pub fn run(args: CliArgs) -> anyhow::Result<()> {
     // Reject reading and writing to the same kafka topic.
-    if args.to == args.from {
+    if args.to.contains(&args.from) || args.route.iter().any(|r| r.target == args.from) {
         return Err(anyhow!("read source and write sink cannot be the same"));
     }
 
-    // Initialise the message source.
+    // Each sink would start its own transactions with the same ID, fencing
+    // the others.
+    if args.produce_args.exactly_once && (!args.route.is_empty() || args.to.len() > 1) {
+        return Err(anyhow!(
+            "--exactly-once cannot be combined with --route or multiple sinks"
+        ));
+    }
+
+    // When committing offsets, the source brokers are needed once the copy has
+    // completed.
+    let commit_brokers = match (&args.from, args.commit) {
+        (Target::Kafka { brokers, .. } | Target::KafkaPattern { brokers, .. }, true) => {
+            Some(brokers.clone())
+        }
+        (_, true) => return Err(anyhow!("--commit requires a kafka source")),
+        (_, false) => None,
+    };
+
+    // Exactly-once copies commit the source offsets to the destination cluster
+    // within each transaction, and resume from them.
+    let exactly_once_brokers = match (
+        &args.from,
+        args.to.as_slice(),
+        args.produce_args.exactly_once,
+    ) {
+        (_, _, false) => None,
+        (_, _, true) if args.commit || args.offset.start_position().is_some() => {
+            return Err(anyhow!(
+                "--exactly-once cannot be combined with --commit, --offset or --time-range"
+            ))
+        }
+        (
+            Target::Kafka { .. } | Target::KafkaPattern { .. },
+            [Target::Kafka { brokers, .. }],
+            true,
+        ) => Some(brokers.clone()),
+        (_, _, true) => {
+            return Err(anyhow!(
+                "--exactly-once requires a kafka source and a kafka sink"
+            ))
+        }
+    };
+
+    // Start from the group's committed offsets when committing, otherwise from
+    // the configured offset range (if any).
+    let start = if args.commit || exactly_once_brokers.is_some() {
+        Some(StartPosition::Committed {
+            group: args.kafka_args.group.clone(),
+            brokers: exactly_once_brokers,
+        })
+    } else {
+        args.offset.start_position()
+    };
+
+    let multi_partition = matches!(args.from, Target::KafkaPattern { .. });
+
+    if args.amplify_args.repeat == 0 || args.amplify_args.streams == 0 {
+        return Err(anyhow!("--repeat and --streams must be at least 1"));
+    }
+
+    // Stdin can only be read once, so there is nothing to re-read on later
+    // passes.
+    if args.amplify_args.repeat > 1 && args.from.is_stdio() {
+        return Err(anyhow!("--repeat cannot be used when reading from stdin"));
+    }
+
+    // Writes to several stdout sinks would be interleaved into one stream.
+    let stdout_sinks = args
+        .to
+        .iter()
+        .chain(args.route.iter().map(|r| &r.target))
+        .filter(|t| t.is_stdio())
+        .count();
+    if stdout_sinks > 1 {
+        return Err(anyhow!("only one sink can write to stdout"));
+    }
+
+    // Initialise the message source, once for each pass over it.
     //
     // This can either be a file, or another kafka topic.
-    let source = source::init(args.from, &args.kafka_args, args.offset.start_offset())
-        .context("failed to initialise copy source")?;
+    let source_target = args.from.clone();
+    let open_source = || -> anyhow::Result<_> {
+        let source = source::init(args.from.clone(), &args.kafka_args, start.clone())
+            .context("failed to initialise copy source")?;
 
-    // Limit messages to the configured offsets
-    let source = args.offset.wrap_iter(source);
+        // Limit messages to the configured offsets
+        Ok(if multi_partition {
+            args.offset.wrap_multi_partition_iter(source)
+        } else {
+            args.offset.wrap_iter(source)
+        })
+    };
+    let source = open_source()?;
 
-    // Initialise the message sink.
-    let mut sink = sink::init(args.to, &args.kafka_args)?;
-
-    // And init a buffer between the source/sink to decouple each of their
-    // respective read/write latencies.
-    let (tx, rx) = std::sync::mpsc::sync_channel(args.buffer);
+    if args.create_topic {
+        create_topic(&args)?;
+    }
 
     let pb = ProgressBar::new_spinner();
     pb.set_style(
-        ProgressStyle::default_spinner()
-            .template("[{elapsed_precise:.cyan/blue}] copied {pos} messages ({per_sec} msg/s)"),
+        ProgressStyle::default_spinner().template(
+            "[{elapsed_precise:.cyan/blue}] copied {pos} messages ({per_sec} msg/s) {msg}",
+        ),
     );
 
+    // Every message read is written once per pass and stream.
     let (_, upper_bound) = source.size_hint();
-    if let Some(u) = upper_bound {
-        pb.set_style(ProgressStyle::default_bar());
+    if let Some(u) = upper_bound.map(|v| v * args.amplify_args.factor()) {
+        pb.set_style(ProgressStyle::default_bar().template("{wide_bar} {pos}/{len} {msg}"));
         pb.set_length(u as _);
     }
 
+    // Initialise the message sink, fanning out to all the sinks if there is
+    // more than one.
+    let mut sink: Box<dyn Sink> = match args.to.as_slice() {
+        [to] => sink::init(to.clone(), &args.kafka_args, &args.produce_args)?,
+        to => {
+            let sinks = to
+                .iter()
+                .map(|t| {
+                    let sink = sink::init(t.clone(), &args.kafka_args, &args.produce_args)?;
+                    Ok((t.to_string(), sink))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+
+            Box::new(Tee::new(sinks, args.buffer, args.tee_policy, pb.clone()))
+        }
+    };
+    if !args.route.is_empty() {
+        let routes = args
+            .route
+            .iter()
+            .map(|r| {
+                let sink = sink::init(r.target.clone(), &args.kafka_args, &args.produce_args)?;
+                Ok((r.condition.clone(), sink))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        sink = Box::new(Router::new(routes, sink));
+    }
+
+    // Save the source topic configuration alongside each dump file, so the
+    // topic can be recreated with "--create-topic" when restoring it.
+    if let Target::Kafka { brokers, topic, .. } = &source_target {
+        for path in args.to.iter().filter_map(|t| match t {
+            Target::Path(v) if !v.is_dir() => Some(v),
+            _ => None,
+        }) {
+            match TopicSpec::describe(brokers.clone(), topic, &args.kafka_args)
+                .and_then(|v| v.save(path))
+            {
+                Ok(_) => pb.println(format!(
+                    "[*] saved topic configuration to {}",
+                    TopicSpec::sidecar_path(path).display()
+                )),
+                Err(e) => pb.println(format!("[-] failed to save topic configuration: {:#}", e)),
+            }
+        }
+    }
+
+    // And init a buffer between the source/sink to decouple each of their
+    // respective read/write latencies.
+    let (tx, rx) = std::sync::mpsc::sync_channel(args.buffer);
+
+    // Pace the writes to reproduce the original message timing, and limit
+    // the write throughput, if configured.
+    let mut clock = ReplayClock::new(&args.replay_args);
+    let mut throttle = Throttle::new(&args.throttle_args);
+    pb.set_message(pacing_status(clock.as_ref(), throttle.as_ref()));
+
     // Spawn a thread to handle the persistence of messages.
     //
     // This decouples the write sink latency from the read side, allowing the
@@ -86,19 +334,37 @@ pub fn run(args: CliArgs) -> anyhow::Result<()> {
     // The number of messages that can be buffered between the read & write
     // side is configurable via a CLI flag before the writer begins applying
     // back-pressure to the read side.
+    //
+    // The thread returns the next offset to read for each source partition it
+    // has written messages from, and the sink's delivery report (if any). A
+    // fatal sink error terminates the thread early.
     let writer_handle = std::thread::spawn({
         let pb = pb.clone();
         move || {
-            while let Ok(msg) = rx.recv() {
+            let mut offsets = BTreeMap::new();
+            while let Ok((pass, msg)) = rx.recv() {
+                if let Some(c) = &mut clock {
+                    c.wait(&msg, pass);
+                }
+
+                if let Some(t) = &mut throttle {
+                    if t.wait(&msg) {
+                        pb.println(format!("[*] throttle adjusted: {}", t));
+                        pb.set_message(pacing_status(clock.as_ref(), Some(t)));
+                    }
+                }
+
                 // Attempt to write the message to the sink, reporting &
                 // retrying any errors that occur.
                 'retry: loop {
                     match sink.write(&msg) {
                         Ok(_) => break 'retry,
+                        Err(e) if e.is::<FatalError>() => return Err(e),
                         Err(e) => pb.println(format!("[-] write error: {}", e).as_str()),
                     }
                     std::thread::sleep(Duration::from_millis(500));
                 }
+                offsets.insert((msg.topic().to_string(), msg.partition()), msg.offset() + 1);
                 pb.inc(1);
             }
 
@@ -108,7 +374,8 @@ pub fn run(args: CliArgs) -> anyhow::Result<()> {
             // terminating the thread.
             loop {
                 match sink.flush() {
-                    Ok(_) => return,
+                    Ok(_) => return Ok((offsets, sink.delivery_report())),
+                    Err(e) if e.is::<FatalError>() => return Err(e),
                     Err(e) => pb.println(format!("[-] write flush error: {}", e).as_str()),
                 }
                 std::thread::sleep(Duration::from_millis(500));
@@ -116,14 +383,66 @@ pub fn run(args: CliArgs) -> anyhow::Result<()> {
         }
     });
 
-    // Drive the copy by reading from the source, and pushing it to the buffer
-    // channel to the sink thread.
-    for maybe_msg in source {
-        match maybe_msg {
-            Ok(v) => {
-                tx.send(v).expect("writer thread has died");
+    // Drive the copy by reading from the source, and pushing it (or its
+    // amplified copies) to the buffer channel to the sink thread, tagged with
+    // the pass over the source it was read in.
+    let amplifier = Amplifier::new(&args.amplify_args);
+    let provenance = Provenance::new(
+        &args.provenance_args,
+        match &source_target {
+            Target::Kafka { brokers, .. } | Target::KafkaPattern { brokers, .. } => {
+                brokers.join(",")
+            }
+            Target::Path(v) => v.display().to_string(),
+            Target::Stdio | Target::Ndjson(_) => source_target.to_string(),
+        },
+    );
+    if let Some(p) = &provenance {
+        pb.println(format!(
+            "[*] adding provenance headers with run id {}",
+            p.run_id()
+        ));
+    }
+
+    let mut source = Some(source);
+    let mut reopen_err = None;
+    'passes: for pass in 0..args.amplify_args.repeat {
+        if args.amplify_args.repeat > 1 {
+            pb.println(format!(
+                "[*] starting pass {} of {}",
+                pass + 1,
+                args.amplify_args.repeat
+            ));
+        }
+
+        let source = match source.take().map_or_else(open_source, Ok) {
+            Ok(v) => v,
+            Err(e) => {
+                reopen_err = Some(e);
+                break;
+            }
+        };
+
+        for maybe_msg in source {
+            match maybe_msg {
+                Ok(mut v) => {
+                    if let Some(p) = &provenance {
+                        p.stamp(&mut v);
+                    }
+
+                    let sent = match &amplifier {
+                        Some(a) => a.copies(&v, pass).all(|m| tx.send((pass, m)).is_ok()),
+                        None => tx.send((pass, v)).is_ok(),
+                    };
+
+                    // The writer thread only stops early upon a fatal error,
+                    // which is reported below.
+                    if !sent {
+                        break 'passes;
+                    }
+                }
+                Err(e) => pb.println(format!("[-] read error: {}", e).as_str()),
             }
-            Err(e) => pb.println(format!("[-] read error: {}", e).as_str()),
         }
     }
 
@@ -132,16 +451,156 @@ pub fn run(args: CliArgs) -> anyhow::Result<()> {
     // Signal the completion to the writer thread and wait for it to flush and
     // exit gracefully.
     drop(tx);
-    writer_handle.join().expect("writer thread died");
+    let (offsets, report) = match writer_handle.join().expect("writer thread died") {
+        Ok(v) => v,
+        Err(e) => {
+            pb.finish_and_clear();
+            return Err(e.context("copy failed"));
+        }
+    };
 
     pb.println("[*] write complete");
 
+    if let Some(e) = reopen_err {
+        pb.finish_and_clear();
+        return Err(e);
+    }
+
+    // Fail the copy if any messages accepted by the sink were subsequently
+    // lost, without committing any offsets.
+    if let Some(report) = report.filter(|v| v.failed_count() > 0) {
+        for (err, n) in &report.failed {
+            pb.println(format!("[-] {} messages failed delivery: {}", n, err).as_str());
+        }
+        if let Some(path) = &args.produce_args.failed_messages {
+            pb.println(
+                format!(
+                    "[*] wrote {} undelivered messages to {}",
+                    report.failed_count() - report.unsaved,
+                    path.display()
+                )
+                .as_str(),
+            );
+        }
+        if report.unsaved > 0 {
+            pb.println(
+                format!("[-] failed to save {} undelivered messages", report.unsaved).as_str(),
+            );
+        }
+        pb.finish_and_clear();
+
+        return Err(anyhow!(
+            "{} of {} messages failed delivery",
+            report.failed_count(),
+            report.failed_count() + report.delivered
+        ));
+    }
+
+    // All writes have been flushed to the sink, so it is now safe to commit the
+    // consumed offsets.
+    if let Some(brokers) = commit_brokers {
+        source::kafka::commit_offsets(brokers, &args.kafka_args, offsets)?;
+        pb.println(format!("[*] committed offsets for group {}", args.kafka_args.group).as_str());
+    }
+
     // Report the final copy stats.
     let count = pb.position();
     let rate = pb.per_sec();
     let elapsed = HumanDuration(pb.elapsed());
     pb.finish_and_clear();
-    println!("[+] complete - copied {count} messages in {elapsed} ({rate} msg/s)");
+    eprintln!("[+] complete - copied {count} messages in {elapsed} ({rate} msg/s)");
 
     Ok(())
 }
+
+/// Create the destination topics of the copy described by `args`, using the
+/// configuration of the source topic and any user-provided overrides.
+fn create_topic(args: &CliArgs) -> anyhow::Result<()> {
+    // Every kafka sink, whether written to directly or through a route, and
+    // each only once.
+    let mut destinations = args
+        .to
+        .iter()
+        .chain(args.route.iter().map(|r| &r.target))
+        .filter_map(|t| match t {
+            Target::Kafka { brokers, topic, .. } => Some((brokers, topic)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    destinations.sort();
+    destinations.dedup();
+
+    if destinations.is_empty() {
+        return Err(anyhow!("--create-topic requires a kafka sink"));
+    }
+
+    let spec = match &args.from {
+        Target::Kafka { brokers, topic, .. } => Some(TopicSpec::describe(
+            brokers.clone(),
+            topic,
+            &args.kafka_args,
+        )?),
+        Target::KafkaPattern { .. } => {
+            return Err(anyhow!("--create-topic requires a single source topic"))
+        }
+        Target::Path(path) => TopicSpec::load(path)?,
+        Target::Stdio | Target::Ndjson(_) => None,
+    };
+
+    let mut spec = match (spec, args.partitions) {
+        (Some(v), _) => v,
+        // Without a saved configuration, fall back to the broker defaults for
+        // everything but the partition count.
+        (None, Some(partitions)) => TopicSpec {
+            partitions,
+            replication_factor: -1,
+            configs: Default::default(),
+        },
+        (None, None) => {
+            return Err(anyhow!(
+                "no topic configuration found for the copy source - specify --partitions to create the topic"
+            ))
+        }
+    };
+
+    if let Some(v) = args.partitions {
+        spec.partitions = v;
+    }
+    if let Some(v) = args.replication_factor {
+        spec.replication_factor = v;
+    }
+
+    for (brokers, topic) in destinations {
+        if spec.create(brokers.clone(), topic, &args.kafka_args)? {
+            eprintln!(
+                "[*] created topic {} ({} partitions, replication factor {}, {} config overrides)",
+                topic,
+                spec.partitions,
+                spec.replication_factor,
+                spec.configs.len()
+            );
+        } else {
+            eprintln!("[*] destination topic {} already exists", topic);
+        }
+    }
+
+    Ok(())
+}
+
+/// Describe the replay speed and throughput limits applied to the copy, for
+/// display in the progress bar.
+fn pacing_status(clock: Option<&ReplayClock>, throttle: Option<&Throttle>) -> String {
+    let parts = [
+        clock.map(ToString::to_string),
+        throttle.map(ToString::to_string),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>();
+
+    if parts.is_empty() {
+        return String::new();
+    }
+
+    format!("({})", parts.join(", "))
+}
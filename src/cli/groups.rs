@@ -0,0 +1,589 @@
+use std::{
+    fs::File,
+    io::{stdin, stdout, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use clap::{ArgGroup, Args, Subcommand};
+
+use crate::consumer_group::{
+    self, GroupDescription, GroupSummary, OffsetChange, OffsetSnapshot, PartitionSelector,
+    ResetStrategy,
+};
+
+use super::common::{parse_time, render_table, KafkaOpts};
+
+/// Inspect consumer groups, and reset, export or import their committed
+/// offsets.
+#[derive(Debug, Args)]
+pub struct CliArgs {
+    #[clap(subcommand)]
+    command: GroupsCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum GroupsCommand {
+    /// List all consumer groups.
+    List(ListArgs),
+
+    /// Show the members, partition assignments, committed offsets and lag of
+    /// a consumer group.
+    Describe(DescribeArgs),
+
+    /// Reset the committed offsets of a consumer group with no active
+    /// members.
+    Reset(ResetArgs),
+
+    /// Export the committed offsets of consumer groups to a JSON file.
+    Export(ExportArgs),
+
+    /// Restore the committed offsets of consumer groups from a JSON file
+    /// written by "groups export", optionally onto another cluster.
+    Import(ImportArgs),
+}
+
+#[derive(Debug, Args)]
+struct ListArgs {
+    /// A comma-delimited set of brokers in "host:port" format.
+    #[clap(required = true)]
+    brokers: Vec<String>,
+
+    /// Output the groups as a JSON array.
+    #[clap(long)]
+    json: bool,
+
+    #[clap(flatten)]
+    kafka_args: KafkaOpts,
+}
+
+#[derive(Debug, Args)]
+struct DescribeArgs {
+    /// A comma-delimited set of brokers in "host:port" format.
+    #[clap(required = true)]
+    brokers: Vec<String>,
+
+    /// The consumer group to describe.
+    //
+    // Renamed to avoid clashing with the --group argument of KafkaOpts.
+    #[clap(name = "target-group", value_name = "GROUP")]
+    group: String,
+
+    /// Output the group description as a single JSON object.
+    #[clap(long)]
+    json: bool,
+
+    #[clap(flatten)]
+    kafka_args: KafkaOpts,
+}
+
+#[derive(Debug, Args)]
+#[clap(group(
+    ArgGroup::new("strategy")
+        .required(true)
+        .args(&["to-timestamp", "to-offset", "shift-by", "to-latest"])
+))]
+struct ResetArgs {
+    /// A comma-delimited set of brokers in "host:port" format.
+    #[clap(required = true)]
+    brokers: Vec<String>,
+
+    /// The consumer group to reset.
+    #[clap(name = "target-group", value_name = "GROUP")]
+    group: String,
+
+    /// Only reset the offsets of this topic ("orders") or partition
+    /// ("orders/3"). May be repeated.
+    ///
+    /// If not specified, every partition with an offset committed by the
+    /// group is reset.
+    #[clap(long = "topic", value_name = "TOPIC[/PARTITION]")]
+    topics: Vec<PartitionSelector>,
+
+    /// Reset to the first message at or after this time, or the end of the
+    /// partition if there is none.
+    ///
+    /// Accepts unix seconds, "<n>s", "<n>ms", an RFC 3339 timestamp, or a
+    /// duration relative to now such as "-2h".
+    #[clap(long, parse(try_from_str = parse_time), allow_hyphen_values = true)]
+    to_timestamp: Option<i64>,
+
+    /// Reset to this offset, clamped to the watermarks of each partition.
+    #[clap(long)]
+    to_offset: Option<i64>,
+
+    /// Move the committed offset forwards by this many offsets, or backwards
+    /// if negative.
+    #[clap(long, allow_hyphen_values = true)]
+    shift_by: Option<i64>,
+
+    /// Reset to the end of each partition, skipping all existing messages.
+    #[clap(long)]
+    to_latest: bool,
+
+    /// Show the offset changes without committing them.
+    #[clap(long)]
+    dry_run: bool,
+
+    #[clap(flatten)]
+    kafka_args: KafkaOpts,
+}
+
+#[derive(Debug, Args)]
+struct ExportArgs {
+    /// A comma-delimited set of brokers in "host:port" format.
+    #[clap(required = true)]
+    brokers: Vec<String>,
+
+    /// The file to write the offsets to, or "-" for stdout.
+    file: PathBuf,
+
+    /// Only export this consumer group. May be repeated.
+    ///
+    /// If not specified, every group with committed offsets is exported.
+    #[clap(long = "only", value_name = "GROUP")]
+    groups: Vec<String>,
+
+    #[clap(flatten)]
+    kafka_args: KafkaOpts,
+}
+
+#[derive(Debug, Args)]
+struct ImportArgs {
+    /// A comma-delimited set of brokers in "host:port" format.
+    #[clap(required = true)]
+    brokers: Vec<String>,
+
+    /// The file written by "groups export", or "-" for stdin.
+    file: PathBuf,
+
+    /// Only import this consumer group. May be repeated.
+    ///
+    /// If not specified, every group in the file is imported.
+    #[clap(long = "only", value_name = "GROUP")]
+    groups: Vec<String>,
+
+    /// Position each partition at the first message at or after the
+    /// timestamp of the message at the exported offset, instead of the
+    /// exported offset itself.
+    ///
+    /// Use this when importing onto a cluster where the same messages have
+    /// different offsets, such as one restored from a dump. Partitions where
+    /// the group had consumed every message are positioned at the end, and
+    /// partitions without a recorded timestamp cannot be translated.
+    #[clap(long)]
+    translate_timestamps: bool,
+
+    /// Show the offset changes without committing them.
+    #[clap(long)]
+    dry_run: bool,
+
+    #[clap(flatten)]
+    kafka_args: KafkaOpts,
+}
+
+impl ResetArgs {
+    fn strategy(&self) -> ResetStrategy {
+        match (self.to_timestamp, self.to_offset, self.shift_by) {
+            (Some(v), _, _) => ResetStrategy::ToTimestamp(v),
+            (_, Some(v), _) => ResetStrategy::ToOffset(v),
+            (_, _, Some(v)) => ResetStrategy::ShiftBy(v),
+            // The "strategy" argument group requires one of the above, or
+            // --to-latest.
+            _ => ResetStrategy::ToLatest,
+        }
+    }
+}
+
+pub fn run(args: CliArgs) -> anyhow::Result<()> {
+    match args.command {
+        GroupsCommand::List(v) => list(v),
+        GroupsCommand::Describe(v) => describe(v),
+        GroupsCommand::Reset(v) => reset(v),
+        GroupsCommand::Export(v) => export(v),
+        GroupsCommand::Import(v) => import(v),
+    }
+}
+
+fn list(args: ListArgs) -> anyhow::Result<()> {
+    let groups = consumer_group::list(args.brokers, &args.kafka_args)?;
+
+    let mut w = stdout();
+    if args.json {
+        serde_json::to_writer(&mut w, &groups).expect("serialisation of groups is infallible");
+        writeln!(&mut w).context("failed to write to stdout")?;
+    } else {
+        write!(&mut w, "{}", render_list(&groups)).context("failed to write to stdout")?;
+    }
+
+    Ok(())
+}
+
+fn render_list(groups: &[GroupSummary]) -> String {
+    let mut rows = vec![["GROUP", "STATE", "PROTOCOL TYPE", "MEMBERS"]
+        .map(String::from)
+        .to_vec()];
+    for g in groups {
+        rows.push(vec![
+            g.name.clone(),
+            g.state.clone(),
+            g.protocol_type.clone(),
+            g.members.to_string(),
+        ]);
+    }
+
+    render_table(&rows)
+}
+
+fn describe(args: DescribeArgs) -> anyhow::Result<()> {
+    let group = consumer_group::describe(args.brokers, &args.group, &args.kafka_args)?;
+
+    let mut w = stdout();
+    if args.json {
+        #[derive(serde::Serialize)]
+        struct Output<'a> {
+            #[serde(flatten)]
+            group: &'a GroupDescription,
+            total_lag: i64,
+        }
+
+        let output = Output {
+            group: &group,
+            total_lag: group.total_lag(),
+        };
+        serde_json::to_writer(&mut w, &output).expect("serialisation of groups is infallible");
+        writeln!(&mut w).context("failed to write to stdout")?;
+    } else {
+        write!(&mut w, "{}", render_description(&group)).context("failed to write to stdout")?;
+    }
+
+    Ok(())
+}
+
+fn render_description(group: &GroupDescription) -> String {
+    let mut out = format!(
+        "Group: {} (state: {}, protocol: {} {})\n\n",
+        group.name, group.state, group.protocol_type, group.protocol
+    );
+
+    out.push_str("Members:\n");
+    if group.members.is_empty() {
+        out.push_str("\tnone\n");
+    }
+    for m in &group.members {
+        out.push_str(&format!(
+            "\t{} (client id: {}, host: {})\n",
+            m.id, m.client_id, m.host
+        ));
+        for tp in &m.assignment {
+            out.push_str(&format!("\t\t{}/{}\n", tp.topic, tp.partition));
+        }
+    }
+    out.push('\n');
+
+    let mut rows = vec![[
+        "TOPIC",
+        "PARTITION",
+        "COMMITTED",
+        "HIGH",
+        "LAG",
+        "CLIENT ID",
+        "HOST",
+    ]
+    .map(String::from)
+    .to_vec()];
+    for p in &group.offsets {
+        let member = group.assignee(&p.topic, p.partition);
+        rows.push(vec![
+            p.topic.clone(),
+            p.partition.to_string(),
+            p.committed
+                .map_or_else(|| "-".to_string(), |v| v.to_string()),
+            p.high.to_string(),
+            p.lag.map_or_else(|| "-".to_string(), |v| v.to_string()),
+            member.map_or_else(|| "-".to_string(), |m| m.client_id.clone()),
+            member.map_or_else(|| "-".to_string(), |m| m.host.clone()),
+        ]);
+    }
+    rows.push(vec![
+        "TOTAL".to_string(),
+        String::new(),
+        String::new(),
+        String::new(),
+        group.total_lag().to_string(),
+        String::new(),
+        String::new(),
+    ]);
+
+    out.push_str(&render_table(&rows));
+    out
+}
+
+fn reset(args: ResetArgs) -> anyhow::Result<()> {
+    let brokers = args.brokers.clone();
+
+    let members = consumer_group::active_members(brokers.clone(), &args.group, &args.kafka_args)?;
+    if members > 0 && !args.dry_run {
+        return Err(anyhow::anyhow!(
+            "consumer group {} has {} active member(s), stop all consumers before resetting offsets",
+            args.group,
+            members
+        ));
+    }
+
+    let changes = consumer_group::plan_reset(
+        brokers.clone(),
+        &args.group,
+        &args.topics,
+        args.strategy(),
+        &args.kafka_args,
+    )?;
+
+    write!(stdout(), "{}", render_changes(&changes)).context("failed to write to stdout")?;
+
+    if args.dry_run {
+        if members > 0 {
+            eprintln!(
+                "[!] consumer group {} has {} active member(s) and cannot be reset",
+                args.group, members
+            );
+        }
+        eprintln!("[+] dry run - no offsets committed");
+        return Ok(());
+    }
+
+    consumer_group::apply_reset(brokers, &args.group, &changes, &args.kafka_args)?;
+    eprintln!(
+        "[+] committed new offsets for {} partition(s) of group {}",
+        changes.len(),
+        args.group
+    );
+
+    Ok(())
+}
+
+fn export(args: ExportArgs) -> anyhow::Result<()> {
+    let snapshot = consumer_group::export(args.brokers, &args.groups, &args.kafka_args)?;
+
+    for group in &args.groups {
+        if !snapshot.groups.iter().any(|g| &g.group == group) {
+            eprintln!("[!] consumer group {} has no committed offsets", group);
+        }
+    }
+
+    let mut w: Box<dyn Write> = if args.file == Path::new("-") {
+        Box::new(stdout())
+    } else {
+        let f = File::create(&args.file)
+            .with_context(|| format!("failed to create {}", args.file.display()))?;
+        Box::new(BufWriter::new(f))
+    };
+    serde_json::to_writer_pretty(&mut w, &snapshot)
+        .context("failed to write consumer group offsets")?;
+    writeln!(&mut w).context("failed to write consumer group offsets")?;
+    w.flush()
+        .context("failed to write consumer group offsets")?;
+
+    eprintln!(
+        "[+] exported offsets of {} consumer group(s)",
+        snapshot.groups.len()
+    );
+
+    Ok(())
+}
+
+fn import(args: ImportArgs) -> anyhow::Result<()> {
+    let brokers = args.brokers.clone();
+
+    let r: Box<dyn Read> = if args.file == Path::new("-") {
+        Box::new(stdin())
+    } else {
+        let f = File::open(&args.file)
+            .with_context(|| format!("failed to open {}", args.file.display()))?;
+        Box::new(BufReader::new(f))
+    };
+    let snapshot: OffsetSnapshot =
+        serde_json::from_reader(r).context("failed to parse consumer group offsets")?;
+
+    for group in &args.groups {
+        if !snapshot.groups.iter().any(|g| &g.group == group) {
+            return Err(anyhow::anyhow!(
+                "consumer group {} not found in file",
+                group
+            ));
+        }
+    }
+    let groups = snapshot
+        .groups
+        .iter()
+        .filter(|g| args.groups.is_empty() || args.groups.contains(&g.group))
+        .collect::<Vec<_>>();
+
+    // Plan every group before committing anything, so a missing partition or
+    // an active group aborts the import without a partial restore.
+    let mut plans = Vec::with_capacity(groups.len());
+    for g in groups {
+        let members = consumer_group::active_members(brokers.clone(), &g.group, &args.kafka_args)?;
+        if members > 0 && !args.dry_run {
+            return Err(anyhow::anyhow!(
+                "consumer group {} has {} active member(s), stop all consumers before importing offsets",
+                g.group,
+                members
+            ));
+        }
+
+        let changes = consumer_group::plan_import(
+            brokers.clone(),
+            g,
+            args.translate_timestamps,
+            &args.kafka_args,
+        )?;
+        plans.push((&g.group, changes));
+    }
+
+    let mut w = stdout();
+    for (i, (group, changes)) in plans.iter().enumerate() {
+        if i > 0 {
+            writeln!(&mut w).context("failed to write to stdout")?;
+        }
+        write!(&mut w, "Group: {}\n{}", group, render_changes(changes))
+            .context("failed to write to stdout")?;
+    }
+
+    if args.dry_run {
+        eprintln!("[+] dry run - no offsets committed");
+        return Ok(());
+    }
+
+    for (group, changes) in &plans {
+        consumer_group::apply_reset(brokers.clone(), group, changes, &args.kafka_args)?;
+    }
+    eprintln!("[+] imported offsets of {} consumer group(s)", plans.len());
+
+    Ok(())
+}
+
+fn render_changes(changes: &[OffsetChange]) -> String {
+    let mut rows = vec![["TOPIC", "PARTITION", "CURRENT", "NEW", "CHANGE"]
+        .map(String::from)
+        .to_vec()];
+    for c in changes {
+        rows.push(vec![
+            c.topic.clone(),
+            c.partition.to_string(),
+            c.current.map_or_else(|| "-".to_string(), |v| v.to_string()),
+            c.new.to_string(),
+            c.current
+                .map_or_else(|| "-".to_string(), |v| format!("{:+}", c.new - v)),
+        ]);
+    }
+
+    render_table(&rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::consumer_group::{Member, PartitionLag, TopicPartition};
+
+    use super::*;
+
+    #[test]
+    fn test_render_description() {
+        let group = GroupDescription {
+            name: "bananas".to_string(),
+            state: "Stable".to_string(),
+            protocol_type: "consumer".to_string(),
+            protocol: "range".to_string(),
+            members: vec![Member {
+                id: "rdkafka-1".to_string(),
+                client_id: "rdkafka".to_string(),
+                host: "/10.0.0.1".to_string(),
+                assignment: vec![TopicPartition {
+                    topic: "orders".to_string(),
+                    partition: 0,
+                }],
+            }],
+            offsets: vec![
+                PartitionLag {
+                    topic: "orders".to_string(),
+                    partition: 0,
+                    committed: Some(10),
+                    high: 42,
+                    lag: Some(32),
+                },
+                PartitionLag {
+                    topic: "payments".to_string(),
+                    partition: 0,
+                    committed: Some(7),
+                    high: 7,
+                    lag: Some(0),
+                },
+            ],
+        };
+
+        assert_eq!(
+            render_description(&group),
+            "\
+Group: bananas (state: Stable, protocol: consumer range)
+
+Members:
+\trdkafka-1 (client id: rdkafka, host: /10.0.0.1)
+\t\torders/0
+
+TOPIC     PARTITION  COMMITTED  HIGH  LAG  CLIENT ID  HOST
+orders    0          10         42    32   rdkafka    /10.0.0.1
+payments  0          7          7     0    -          -
+TOTAL                                 32
+"
+        );
+    }
+
+    #[test]
+    fn test_render_changes() {
+        let changes = [
+            OffsetChange {
+                topic: "orders".to_string(),
+                partition: 0,
+                current: Some(42),
+                new: 10,
+            },
+            OffsetChange {
+                topic: "orders".to_string(),
+                partition: 1,
+                current: Some(7),
+                new: 8,
+            },
+            OffsetChange {
+                topic: "orders".to_string(),
+                partition: 2,
+                current: None,
+                new: 0,
+            },
+        ];
+
+        assert_eq!(
+            render_changes(&changes),
+            "\
+TOPIC   PARTITION  CURRENT  NEW  CHANGE
+orders  0          42       10   -32
+orders  1          7        8    +1
+orders  2          -        0    -
+"
+        );
+    }
+
+    #[test]
+    fn test_render_list() {
+        let groups = [GroupSummary {
+            name: "bananas".to_string(),
+            state: "Empty".to_string(),
+            protocol_type: "consumer".to_string(),
+            members: 0,
+        }];
+
+        assert_eq!(
+            render_list(&groups),
+            "GROUP    STATE  PROTOCOL TYPE  MEMBERS\nbananas  Empty  consumer       0\n"
+        );
+    }
+}
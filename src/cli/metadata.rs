@@ -1,9 +1,14 @@
+use std::io::{stdout, Write};
+
 use anyhow::Context;
 use clap::Args;
 use rdkafka::{
     config::FromClientConfig,
     consumer::{BaseConsumer, Consumer},
+    metadata::Metadata,
 };
+use regex::Regex;
+use serde::Serialize;
 
 use super::common::KafkaOpts;
 
@@ -13,12 +18,147 @@ pub struct CliArgs {
     /// metadata.
     brokers: Vec<String>,
 
+    /// Output the metadata as a single JSON object.
+    #[clap(long)]
+    json: bool,
+
+    /// Only show topics with a name matching this regex.
+    ///
+    /// The regex matches anywhere within the topic name unless anchored with
+    /// '^' and '$'.
+    #[clap(long)]
+    topic: Option<Regex>,
+
+    /// Hide internal topics (those prefixed with "__", such as
+    /// "__consumer_offsets").
+    #[clap(long)]
+    exclude_internal: bool,
+
+    /// Only show partitions with fewer in-sync replicas than replicas.
+    ///
+    /// When combined with --offline, partitions matching either are shown.
+    /// Topics without any matching partitions are hidden.
+    #[clap(long)]
+    under_replicated: bool,
+
+    /// Only show partitions without a leader.
+    ///
+    /// When combined with --under-replicated, partitions matching either are
+    /// shown. Topics without any matching partitions are hidden.
+    #[clap(long)]
+    offline: bool,
+
     #[clap(flatten)]
     kafka_opts: KafkaOpts,
 }
 
+/// The metadata of a cluster, as seen by the broker that served it.
+#[derive(Debug, Serialize)]
+struct ClusterMetadata {
+    orig_broker_id: i32,
+    orig_broker_name: String,
+    brokers: Vec<BrokerMetadata>,
+    topics: Vec<TopicMetadata>,
+}
+
+#[derive(Debug, Serialize)]
+struct BrokerMetadata {
+    id: i32,
+    host: String,
+    port: i32,
+}
+
+#[derive(Debug, Serialize)]
+struct TopicMetadata {
+    name: String,
+    error: Option<String>,
+    partitions: Vec<PartitionMetadata>,
+}
+
+#[derive(Debug, Serialize)]
+struct PartitionMetadata {
+    id: i32,
+
+    /// The broker ID of the partition leader, or -1 if the partition is
+    /// offline.
+    leader: i32,
+    replicas: Vec<i32>,
+    isr: Vec<i32>,
+    error: Option<String>,
+}
+
+impl PartitionMetadata {
+    fn is_under_replicated(&self) -> bool {
+        self.isr.len() < self.replicas.len()
+    }
+
+    fn is_offline(&self) -> bool {
+        self.leader < 0
+    }
+}
+
+impl From<&Metadata> for ClusterMetadata {
+    fn from(meta: &Metadata) -> Self {
+        Self {
+            orig_broker_id: meta.orig_broker_id(),
+            orig_broker_name: meta.orig_broker_name().to_string(),
+            brokers: meta
+                .brokers()
+                .iter()
+                .map(|b| BrokerMetadata {
+                    id: b.id(),
+                    host: b.host().to_string(),
+                    port: b.port(),
+                })
+                .collect(),
+            topics: meta
+                .topics()
+                .iter()
+                .map(|t| TopicMetadata {
+                    name: t.name().to_string(),
+                    error: t.error().map(|e| format!("{:?}", e)),
+                    partitions: t
+                        .partitions()
+                        .iter()
+                        .map(|p| PartitionMetadata {
+                            id: p.id(),
+                            leader: p.leader(),
+                            replicas: p.replicas().to_vec(),
+                            isr: p.isr().to_vec(),
+                            error: p.error().map(|e| format!("{:?}", e)),
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl ClusterMetadata {
+    /// Remove the topics and partitions not selected by the filters in
+    /// `args`.
+    fn filter(&mut self, args: &CliArgs) {
+        self.topics.retain(|t| {
+            args.topic.as_ref().is_none_or(|re| re.is_match(&t.name))
+                && !(args.exclude_internal && t.name.starts_with("__"))
+        });
+
+        if !args.under_replicated && !args.offline {
+            return;
+        }
+
+        for t in &mut self.topics {
+            t.partitions.retain(|p| {
+                (args.under_replicated && p.is_under_replicated())
+                    || (args.offline && p.is_offline())
+            });
+        }
+        self.topics.retain(|t| !t.partitions.is_empty());
+    }
+}
+
 pub fn run(v: CliArgs) -> Result<(), anyhow::Error> {
-    let config = v.kafka_opts.new_kafka_config(v.brokers);
+    let config = v.kafka_opts.new_consumer_config(v.brokers.clone());
 
     let consumer =
         BaseConsumer::from_config(&config).context("failed to initialise kafka consumer")?;
@@ -27,44 +167,162 @@ pub fn run(v: CliArgs) -> Result<(), anyhow::Error> {
         .fetch_metadata(None, v.kafka_opts.timeout)
         .context("failed to read cluster metadata")?;
 
+    let mut meta = ClusterMetadata::from(&meta);
+    meta.filter(&v);
+
+    if v.json {
+        let mut w = stdout();
+        serde_json::to_writer(&mut w, &meta).expect("serialisation of metadata is infallible");
+        writeln!(&mut w).context("failed to write to stdout")?;
+        return Ok(());
+    }
+
     println!(
         "[+] metadata retrieved (src: {}, id: {})",
-        meta.orig_broker_name(),
-        meta.orig_broker_id()
+        meta.orig_broker_name, meta.orig_broker_id
     );
     println!();
 
     println!("Brokers:");
-    for broker in meta.brokers() {
+    for broker in &meta.brokers {
         println!(
             "\tid {id} -> {host}:{port}",
-            id = broker.id(),
-            host = broker.host(),
-            port = broker.port()
+            id = broker.id,
+            host = broker.host,
+            port = broker.port
         );
     }
     println!();
 
     println!("Topics:");
-    for topic in meta.topics() {
-        println!("\t{}", topic.name());
-        if let Some(e) = topic.error() {
-            println!("\t\terror: {:?}", e);
+    for topic in &meta.topics {
+        println!("\t{}", topic.name);
+        if let Some(e) = &topic.error {
+            println!("\t\terror: {}", e);
         }
 
-        for p in topic.partitions() {
+        for p in &topic.partitions {
             println!(
                 "\t\tpartition {}, leader {}, replicas {:?} (in sync: {:?})",
-                p.id(),
-                p.leader(),
-                p.replicas(),
-                p.isr()
+                p.id, p.leader, p.replicas, p.isr
             );
-            if let Some(e) = p.error() {
-                println!("\t\t\terror: {:?}", e);
+            if let Some(e) = &p.error {
+                println!("\t\t\terror: {}", e);
             }
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Debug, Parser)]
+    struct Cli {
+        #[clap(flatten)]
+        args: CliArgs,
+    }
+
+    fn partition(id: i32, leader: i32, replicas: &[i32], isr: &[i32]) -> PartitionMetadata {
+        PartitionMetadata {
+            id,
+            leader,
+            replicas: replicas.to_vec(),
+            isr: isr.to_vec(),
+            error: None,
+        }
+    }
+
+    fn topic(name: &str, partitions: Vec<PartitionMetadata>) -> TopicMetadata {
+        TopicMetadata {
+            name: name.to_string(),
+            error: None,
+            partitions,
+        }
+    }
+
+    fn cluster() -> ClusterMetadata {
+        ClusterMetadata {
+            orig_broker_id: 1,
+            orig_broker_name: "broker:9092/1".to_string(),
+            brokers: vec![],
+            topics: vec![
+                topic(
+                    "orders",
+                    vec![
+                        partition(0, 1, &[1, 2, 3], &[1, 2, 3]),
+                        partition(1, 2, &[2, 3, 1], &[2]),
+                    ],
+                ),
+                topic("payments", vec![partition(0, -1, &[3, 1], &[])]),
+                topic("__consumer_offsets", vec![partition(0, 1, &[1], &[1])]),
+            ],
+        }
+    }
+
+    /// Return the topic and partitions remaining after filtering with the
+    /// given command line flags.
+    fn filtered(flags: &[&str]) -> Vec<(String, Vec<i32>)> {
+        let args = Cli::parse_from(["metadata", "broker:9092"].iter().chain(flags)).args;
+
+        let mut meta = cluster();
+        meta.filter(&args);
+
+        meta.topics
+            .into_iter()
+            .map(|t| (t.name, t.partitions.iter().map(|p| p.id).collect()))
+            .collect()
+    }
+
+    fn want(v: &[(&str, &[i32])]) -> Vec<(String, Vec<i32>)> {
+        v.iter().map(|(t, p)| (t.to_string(), p.to_vec())).collect()
+    }
+
+    #[test]
+    fn test_filter() {
+        assert_eq!(
+            filtered(&[]),
+            want(&[
+                ("orders", &[0, 1]),
+                ("payments", &[0]),
+                ("__consumer_offsets", &[0])
+            ])
+        );
+
+        assert_eq!(filtered(&["--topic", "^pay"]), want(&[("payments", &[0])]));
+
+        assert_eq!(
+            filtered(&["--exclude-internal"]),
+            want(&[("orders", &[0, 1]), ("payments", &[0])])
+        );
+
+        assert_eq!(
+            filtered(&["--under-replicated"]),
+            want(&[("orders", &[1]), ("payments", &[0])])
+        );
+
+        assert_eq!(filtered(&["--offline"]), want(&[("payments", &[0])]));
+
+        assert_eq!(
+            filtered(&["--offline", "--under-replicated", "--topic", "orders"]),
+            want(&[("orders", &[1])])
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let meta = ClusterMetadata {
+            topics: vec![topic("orders", vec![partition(0, 1, &[1, 2], &[1])])],
+            ..cluster()
+        };
+
+        assert_eq!(
+            serde_json::to_string(&meta).unwrap(),
+            r#"{"orig_broker_id":1,"orig_broker_name":"broker:9092/1","brokers":[],"topics":[{"name":"orders","error":null,"partitions":[{"id":0,"leader":1,"replicas":[1,2],"isr":[1],"error":null}]}]}"#
+        );
+    }
+}
@@ -1,5 +1,7 @@
 pub mod common;
 pub mod cp;
+pub mod groups;
 pub mod metadata;
+pub mod offsets;
 pub mod read;
 pub mod write;
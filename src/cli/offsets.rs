@@ -0,0 +1,280 @@
+use std::io::{stdout, Write};
+
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, SecondsFormat, Utc};
+use clap::Args;
+use serde::Serialize;
+
+use crate::source::kafka::{self, PartitionOffsets};
+
+use super::common::{parse_time, render_table, KafkaOpts, Target};
+
+/// Show the watermarks, message counts and first/last message timestamps of
+/// each partition of a topic.
+#[derive(Debug, Args)]
+pub struct CliArgs {
+    /// The topic to inspect, in the form "kafka://brokers/topic", or
+    /// "kafka://brokers/topic/partition" for a single partition.
+    ///
+    /// All partitions of every topic matching a glob ("kafka://brokers/orders.*")
+    /// or a regex prefixed with '~' ("kafka://brokers/~orders\..*") can be
+    /// inspected at once.
+    target: Target,
+
+    /// Also resolve the offset of the first message at or after this time in
+    /// each partition, and the number of messages since.
+    ///
+    /// Accepts unix seconds, "<n>s", "<n>ms", an RFC 3339 timestamp, or a
+    /// duration relative to now such as "-2h".
+    #[clap(long, parse(try_from_str = parse_time), allow_hyphen_values = true)]
+    at: Option<i64>,
+
+    /// Output the offsets as a single JSON object.
+    #[clap(long)]
+    json: bool,
+
+    #[clap(flatten)]
+    kafka_args: KafkaOpts,
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    partitions: Vec<PartitionReport>,
+    total: Total,
+}
+
+#[derive(Debug, Serialize)]
+struct PartitionReport {
+    #[serde(flatten)]
+    offsets: PartitionOffsets,
+
+    /// The number of offsets between the watermarks.
+    count: i64,
+
+    /// The offset resolved for --at, or the high watermark if no message was
+    /// written at or after that time.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    at_offset: Option<i64>,
+
+    /// The number of offsets from `at_offset` to the high watermark.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    at_count: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct Total {
+    count: i64,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    at_count: Option<i64>,
+}
+
+impl Report {
+    fn new(offsets: Vec<PartitionOffsets>, at: Option<&[Option<i64>]>) -> Self {
+        let partitions = offsets
+            .into_iter()
+            .enumerate()
+            .map(|(i, offsets)| {
+                let at_offset = at.map(|v| v[i].unwrap_or(offsets.high));
+                PartitionReport {
+                    count: offsets.count(),
+                    at_count: at_offset.map(|v| offsets.high - v),
+                    at_offset,
+                    offsets,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let total = Total {
+            count: partitions.iter().map(|p| p.count).sum(),
+            at_count: at.map(|_| partitions.iter().filter_map(|p| p.at_count).sum()),
+        };
+
+        Self { partitions, total }
+    }
+
+    /// Render the report as a table with aligned columns.
+    fn render_table(&self) -> String {
+        let show_at = self.total.at_count.is_some();
+
+        let mut header = vec![
+            "TOPIC",
+            "PARTITION",
+            "LOW",
+            "HIGH",
+            "COUNT",
+            "FIRST TIMESTAMP",
+            "LAST TIMESTAMP",
+        ];
+        if show_at {
+            header.extend(["AT OFFSET", "AT COUNT"]);
+        }
+
+        let mut rows = vec![header.into_iter().map(String::from).collect::<Vec<_>>()];
+        for p in &self.partitions {
+            let mut row = vec![
+                p.offsets.topic.clone(),
+                p.offsets.partition.to_string(),
+                p.offsets.low.to_string(),
+                p.offsets.high.to_string(),
+                p.count.to_string(),
+                format_timestamp(p.offsets.first_timestamp),
+                format_timestamp(p.offsets.last_timestamp),
+            ];
+            if show_at {
+                row.push(p.at_offset.unwrap_or_default().to_string());
+                row.push(p.at_count.unwrap_or_default().to_string());
+            }
+            rows.push(row);
+        }
+
+        let mut total = vec![String::new(); rows[0].len()];
+        total[0] = "TOTAL".to_string();
+        total[4] = self.total.count.to_string();
+        if let Some(v) = self.total.at_count {
+            total[8] = v.to_string();
+        }
+        rows.push(total);
+
+        render_table(&rows)
+    }
+}
+
+fn format_timestamp(ts: Option<i64>) -> String {
+    ts.and_then(DateTime::<Utc>::from_timestamp_millis)
+        .map(|v| v.to_rfc3339_opts(SecondsFormat::Millis, true))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+pub fn run(args: CliArgs) -> anyhow::Result<()> {
+    let brokers = match &args.target {
+        Target::Kafka { brokers, .. } | Target::KafkaPattern { brokers, .. } => brokers.clone(),
+        _ => return Err(anyhow!("offsets requires a kafka topic")),
+    };
+    let partitions = kafka::resolve_partitions(&brokers, &args.target, &args.kafka_args)?;
+
+    let offsets = kafka::partition_offsets(brokers.clone(), &partitions, &args.kafka_args)?;
+
+    let at = match args.at {
+        Some(ts) => {
+            let mut resolved = kafka::offsets_for_time(brokers, &partitions, ts, &args.kafka_args)?;
+            Some(
+                partitions
+                    .iter()
+                    .map(|tp| resolved.remove(tp).flatten())
+                    .collect::<Vec<_>>(),
+            )
+        }
+        None => None,
+    };
+
+    let report = Report::new(offsets, at.as_deref());
+
+    let mut w = stdout();
+    if args.json {
+        serde_json::to_writer(&mut w, &report).expect("serialisation of offsets is infallible");
+        writeln!(&mut w).context("failed to write to stdout")?;
+    } else {
+        write!(&mut w, "{}", report.render_table()).context("failed to write to stdout")?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offsets(partition: i32, low: i64, high: i64, ts: Option<(i64, i64)>) -> PartitionOffsets {
+        PartitionOffsets {
+            topic: "orders".to_string(),
+            partition,
+            low,
+            high,
+            first_timestamp: ts.map(|v| v.0),
+            last_timestamp: ts.map(|v| v.1),
+        }
+    }
+
+    #[test]
+    fn test_table() {
+        let report = Report::new(
+            vec![
+                offsets(0, 10, 110, Some((1663602628526, 1663602700000))),
+                offsets(1, 0, 0, None),
+            ],
+            None,
+        );
+
+        assert_eq!(
+            report.render_table(),
+            "\
+TOPIC   PARTITION  LOW  HIGH  COUNT  FIRST TIMESTAMP           LAST TIMESTAMP
+orders  0          10   110   100    2022-09-19T15:50:28.526Z  2022-09-19T15:51:40.000Z
+orders  1          0    0     0      -                         -
+TOTAL                         100
+"
+        );
+    }
+
+    #[test]
+    fn test_at() {
+        let report = Report::new(
+            vec![offsets(0, 10, 110, None), offsets(1, 0, 5, None)],
+            // No messages at or after the time in partition 1.
+            Some(&[Some(60), None]),
+        );
+
+        assert_eq!(report.partitions[0].at_offset, Some(60));
+        assert_eq!(report.partitions[0].at_count, Some(50));
+        assert_eq!(report.partitions[1].at_offset, Some(5));
+        assert_eq!(report.partitions[1].at_count, Some(0));
+        assert_eq!(report.total.count, 105);
+        assert_eq!(report.total.at_count, Some(50));
+
+        let table = report.render_table();
+        let total = table.lines().last().unwrap();
+        assert_eq!(
+            total.split_whitespace().collect::<Vec<_>>(),
+            ["TOTAL", "105", "50"]
+        );
+    }
+
+    #[test]
+    fn test_parse_relative_at() {
+        use clap::Parser;
+        use std::time::{SystemTime, UNIX_EPOCH};
+
+        #[derive(Debug, Parser)]
+        struct Cli {
+            #[clap(flatten)]
+            args: CliArgs,
+        }
+
+        let two_hours_ago = || {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as i64
+                - 2 * 60 * 60 * 1000
+        };
+
+        let before = two_hours_ago();
+        let cli = Cli::try_parse_from(["ktool", "kafka://127.0.0.1/orders", "--at", "-2h"])
+            .expect("relative --at is accepted");
+        let after = two_hours_ago();
+
+        let at = cli.args.at.expect("--at is set");
+        assert!((before..=after).contains(&at));
+    }
+
+    #[test]
+    fn test_json() {
+        let report = Report::new(vec![offsets(0, 10, 110, Some((1, 2)))], None);
+
+        assert_eq!(
+            serde_json::to_string(&report).unwrap(),
+            r#"{"partitions":[{"topic":"orders","partition":0,"low":10,"high":110,"first_timestamp":1,"last_timestamp":2,"count":100}],"total":{"count":100}}"#
+        );
+    }
+}
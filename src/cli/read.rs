@@ -1,9 +1,17 @@
-use std::io::{stdout, BufWriter, Write};
+use std::{
+    collections::BTreeMap,
+    io::{stdout, BufWriter, Write},
+    ops::RangeInclusive,
+};
 
-use anyhow::Context;
+use anyhow::{anyhow, Context};
 use clap::Args;
 
-use crate::{json_output::JsonMessage, source};
+use crate::{
+    json_output::JsonMessage,
+    message::Message,
+    source::{self, kafka::SkipInspector, StartPosition},
+};
 
 use super::common::{OffsetClap, Target};
 
@@ -19,11 +27,22 @@ pub struct CliArgs {
     ///
     ///   - File: /path/to/file.bin
     ///
+    ///   - Stdin: "-", reading a dump file streamed through a pipe
+    ///
+    ///   - NDJSON: "ndjson:/path/to/file.jsonl", or "ndjson:-" for stdin
+    ///
     /// Where a Kafka source can specify one or more comma-delimited broker
     /// addresses, a topic, and a optional partition number. Example:
     /// "kafka://127.0.0.1:9092,another:9092/my_topic/0".
     ///
+    /// Instead of a single topic, all partitions of every topic matching a
+    /// glob ("kafka://brokers/orders.*") or a regex prefixed with '~'
+    /// ("kafka://brokers/~orders\..*") can be read at once.
+    ///
     /// Where a file source can be a absolute, or relative file path.
+    ///
+    /// An NDJSON source contains one JSON message per line, in the format
+    /// output by "read --json".
     from: Target,
 
     /// Output messages as newline delimited JSON objects.
@@ -33,6 +52,34 @@ pub struct CliArgs {
     #[clap(long)]
     json: bool,
 
+    /// Start reading from the offset committed by the specified consumer
+    /// group, showing the messages that group will process next.
+    ///
+    /// The group's committed offset and lag are printed before reading. The
+    /// group is never joined, and its committed offsets are never modified.
+    ///
+    /// Requires a Kafka source, and cannot be combined with --offset or
+    /// --time-range.
+    #[clap(long, conflicts_with_all = &["offset", "time-range"])]
+    from_group: Option<String>,
+
+    /// Report offsets within a partition that were never returned by the
+    /// consumer, and why.
+    ///
+    /// Kafka never returns transaction control markers or compacted messages,
+    /// and with "--isolation read_committed" never returns messages from
+    /// aborted or open transactions. Skipped offsets (including those between
+    /// the start position and the first message, and between the last message
+    /// and the high watermark) are re-read with "read_uncommitted" isolation to
+    /// identify aborted and open transactions, with the rest reported as
+    /// transaction markers. In a compacted topic, markers and compacted
+    /// messages cannot be told apart.
+    ///
+    /// Skipped offsets are printed inline with the messages, or to stderr when
+    /// using --json. Requires a Kafka source.
+    #[clap(long)]
+    show_gaps: bool,
+
     #[clap(flatten)]
     offset: OffsetClap,
 
@@ -41,14 +88,72 @@ pub struct CliArgs {
 }
 
 pub fn run(args: CliArgs) -> anyhow::Result<()> {
+    if args.from_group.is_some()
+        && matches!(
+            args.from,
+            Target::Path(_) | Target::Stdio | Target::Ndjson(_)
+        )
+    {
+        return Err(anyhow!("--from-group requires a kafka source"));
+    }
+
+    if args.show_gaps
+        && matches!(
+            args.from,
+            Target::Path(_) | Target::Stdio | Target::Ndjson(_)
+        )
+    {
+        return Err(anyhow!("--show-gaps requires a kafka source"));
+    }
+
+    let start = match args.from_group {
+        Some(group) => Some(StartPosition::Committed {
+            group,
+            brokers: None,
+        }),
+        None => args.offset.start_position(),
+    };
+
+    let multi_partition = matches!(args.from, Target::KafkaPattern { .. });
+
+    // Resolve where reading each partition begins and ends, so offsets never
+    // returned at either end of the read can be reported.
+    let mut gaps = GapTracker::default();
+    let mut ends = BTreeMap::new();
+    let mut inspector = None;
+    if let (true, Target::Kafka { brokers, .. } | Target::KafkaPattern { brokers, .. }) =
+        (args.show_gaps, &args.from)
+    {
+        let partitions = source::kafka::resolve_partitions(brokers, &args.from, &args.kafka_args)?;
+        let positions = source::kafka::read_positions(
+            brokers.clone(),
+            &partitions,
+            start.as_ref(),
+            &args.kafka_args,
+        )?;
+
+        for (tp, (first, high)) in positions {
+            if let Some(end) = args.offset.end_offset(high) {
+                ends.insert(tp.clone(), end);
+            }
+            gaps.expect(tp, first);
+        }
+
+        inspector = Some(SkipInspector::new(brokers.clone(), &args.kafka_args)?);
+    }
+
     // Initialise the message source.
     //
     // This can either be a file, or another kafka topic.
-    let source = source::init(args.from, &args.kafka_args, args.offset.start_offset())
+    let source = source::init(args.from, &args.kafka_args, start)
         .context("failed to initialise copy source")?;
 
     // Limit messages to the configured offsets
-    let source = args.offset.wrap_iter(source);
+    let source = if multi_partition {
+        args.offset.wrap_multi_partition_iter(source)
+    } else {
+        args.offset.wrap_iter(source)
+    };
 
     let mut w = BufWriter::new(stdout());
 
@@ -56,6 +161,11 @@ pub fn run(args: CliArgs) -> anyhow::Result<()> {
     for maybe_msg in source {
         match maybe_msg {
             Ok(v) => {
+                if let (Some(skipped), Some(inspector)) = (gaps.observe(&v), inspector.as_mut()) {
+                    let tp = (v.topic(), v.partition());
+                    report_skipped(&mut w, inspector, args.json, tp, skipped)?;
+                }
+
                 // Print this message.
                 if args.json {
                     // Wrap this in a JsonMessage to generate the field
@@ -71,7 +181,131 @@ pub fn run(args: CliArgs) -> anyhow::Result<()> {
         }
     }
 
+    // Report any offsets left unread at the end of each partition.
+    if let Some(inspector) = inspector.as_mut() {
+        for (tp, end) in ends {
+            if let Some(skipped) = gaps.remaining(&tp, end) {
+                report_skipped(&mut w, inspector, args.json, (&tp.0, tp.1), skipped)?;
+            }
+        }
+    }
+
     w.flush().expect("failed to flush stdout");
 
     Ok(())
 }
+
+/// Report why each offset in `skipped` was never returned from `tp`, inline
+/// with the messages written to `w`, or to stderr when outputting JSON.
+fn report_skipped(
+    w: &mut impl Write,
+    inspector: &mut SkipInspector<'_>,
+    json: bool,
+    tp: (&str, i32),
+    skipped: RangeInclusive<i64>,
+) -> anyhow::Result<()> {
+    for (range, reason) in inspector.inspect(tp.0, tp.1, skipped)? {
+        let report = format!(
+            "[~] {}/{}: offsets {}..={} skipped ({})",
+            tp.0,
+            tp.1,
+            range.start(),
+            range.end(),
+            reason
+        );
+        if json {
+            eprintln!("{}", report);
+        } else {
+            writeln!(w, "{}", report).unwrap();
+        }
+    }
+
+    Ok(())
+}
+
+/// Tracks the next offset expected from each partition, identifying offsets
+/// that were never returned.
+#[derive(Debug, Default)]
+struct GapTracker(BTreeMap<(String, i32), i64>);
+
+impl GapTracker {
+    /// Expect the first message read from `tp` to be at `offset`.
+    fn expect(&mut self, tp: (String, i32), offset: i64) {
+        self.0.insert(tp, offset);
+    }
+
+    /// Record `msg`, returning the inclusive range of offsets skipped since
+    /// the previous message read from the same partition (or the expected
+    /// first offset), if any.
+    fn observe(&mut self, msg: &Message) -> Option<RangeInclusive<i64>> {
+        let next = self
+            .0
+            .insert((msg.topic().to_string(), msg.partition()), msg.offset() + 1)?;
+
+        (msg.offset() > next).then(|| next..=msg.offset() - 1)
+    }
+
+    /// Return the inclusive range of offsets before `end` never read from
+    /// `tp`, if any.
+    fn remaining(&self, tp: &(String, i32), end: i64) -> Option<RangeInclusive<i64>> {
+        let next = *self.0.get(tp)?;
+        (end > next).then(|| next..=end - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::message::test_util::message;
+
+    use super::*;
+
+    fn msg(topic: &str, partition: i32, offset: i64) -> Message {
+        message()
+            .topic(topic)
+            .partition(partition)
+            .offset(offset)
+            .build()
+    }
+
+    #[test]
+    fn test_gap_tracker() {
+        let mut gaps = GapTracker::default();
+
+        // The first message in a partition has nothing to compare against.
+        assert_eq!(gaps.observe(&msg("bananas", 0, 10)), None);
+        assert_eq!(gaps.observe(&msg("bananas", 0, 11)), None);
+
+        // A single transaction marker.
+        assert_eq!(gaps.observe(&msg("bananas", 0, 13)), Some(12..=12));
+
+        // Partitions and topics are tracked independently.
+        assert_eq!(gaps.observe(&msg("bananas", 1, 42)), None);
+        assert_eq!(gaps.observe(&msg("platanos", 0, 1)), None);
+
+        // An aborted transaction and its marker.
+        assert_eq!(gaps.observe(&msg("bananas", 0, 20)), Some(14..=19));
+        assert_eq!(gaps.observe(&msg("bananas", 1, 43)), None);
+
+        // Nothing is known about the end of the partition unless given.
+        assert_eq!(gaps.remaining(&("bananas".to_string(), 0), 21), None);
+        assert_eq!(
+            gaps.remaining(&("bananas".to_string(), 0), 23),
+            Some(21..=22)
+        );
+        assert_eq!(gaps.remaining(&("platanos".to_string(), 1), 10), None);
+    }
+
+    #[test]
+    fn test_gap_tracker_expected_start() {
+        let mut gaps = GapTracker::default();
+        gaps.expect(("bananas".to_string(), 0), 5);
+        gaps.expect(("bananas".to_string(), 1), 0);
+
+        // The first message is compared against the expected start.
+        assert_eq!(gaps.observe(&msg("bananas", 0, 8)), Some(5..=7));
+        assert_eq!(gaps.observe(&msg("bananas", 0, 9)), None);
+
+        // A partition that returns nothing leaves everything unread.
+        assert_eq!(gaps.remaining(&("bananas".to_string(), 1), 4), Some(0..=3));
+    }
+}
@@ -1,4 +1,251 @@
+use std::{
+    collections::BTreeMap,
+    io::{stdin, BufRead},
+    path::PathBuf,
+    str::FromStr,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Context};
 use clap::Args;
+use thiserror::Error;
+
+use crate::{
+    message::{Message, Timestamp},
+    sink::{kafka::Kafka, FatalError, Sink},
+};
+
+use super::common::{parse_timestamp, KafkaOpts, ProduceOpts, Target};
 
+/// Write messages to a Kafka topic.
+///
+/// The message payload is either given as an argument, read from a file, or
+/// otherwise read from stdin, with each line written as a separate message.
 #[derive(Debug, Args)]
-pub struct CliArgs {}
+pub struct CliArgs {
+    /// The Kafka topic to write to, in the form "kafka://brokers/topic" or
+    /// "kafka://brokers/topic/partition".
+    to: Target,
+
+    /// The message payload.
+    ///
+    /// If neither a payload nor --file is given, each line read from stdin
+    /// is written as a message.
+    #[clap(conflicts_with = "file")]
+    payload: Option<String>,
+
+    /// Write the contents of this file as the payload of a single message.
+    #[clap(long)]
+    file: Option<PathBuf>,
+
+    /// The message key.
+    #[clap(long, conflicts_with = "delimiter")]
+    key: Option<String>,
+
+    /// Add a header to every message, in the form "<name>=<value>". Can be
+    /// repeated.
+    #[clap(long)]
+    header: Vec<Header>,
+
+    /// The partition to write to, instead of the one chosen by the
+    /// partitioner.
+    ///
+    /// Cannot be used when the destination specifies a partition.
+    #[clap(long)]
+    partition: Option<i32>,
+
+    /// Split each line read from stdin into a key and payload at the first
+    /// occurrence of this delimiter.
+    ///
+    /// Lines without the delimiter are written without a key.
+    #[clap(long, short = 'K')]
+    delimiter: Option<String>,
+
+    /// The message timestamp, instead of the time it is produced.
+    ///
+    /// Accepts unix seconds, "<n>s", "<n>ms", or an RFC 3339 timestamp such
+    /// as "2022-06-01T12:00:00Z".
+    #[clap(long, parse(try_from_str = parse_timestamp))]
+    timestamp: Option<i64>,
+
+    #[clap(flatten)]
+    kafka_args: KafkaOpts,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum HeaderError {
+    #[error("invalid header (expected '<name>=<value>')")]
+    Format,
+}
+
+/// A message header given on the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    name: String,
+    value: String,
+}
+
+impl FromStr for Header {
+    type Err = HeaderError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = s
+            .split_once('=')
+            .filter(|(name, _)| !name.is_empty())
+            .ok_or(HeaderError::Format)?;
+
+        Ok(Self {
+            name: name.to_string(),
+            value: value.to_string(),
+        })
+    }
+}
+
+pub fn run(args: CliArgs) -> anyhow::Result<()> {
+    let (brokers, topic, partition) = match args.to.clone() {
+        Target::Kafka {
+            brokers,
+            topic,
+            partition,
+        } => (brokers, topic, partition),
+        _ => return Err(anyhow!("write requires a kafka destination")),
+    };
+
+    let partition = match (partition, args.partition) {
+        (Some(_), Some(_)) => {
+            return Err(anyhow!(
+                "--partition cannot be used when the destination specifies a partition"
+            ))
+        }
+        (a, b) => a.or(b),
+    };
+
+    let headers = (!args.header.is_empty()).then(|| {
+        args.header
+            .iter()
+            .map(|h| (h.name.clone(), h.value.clone().into_bytes()))
+            .collect::<BTreeMap<_, _>>()
+    });
+
+    let new_message = |key: Option<Vec<u8>>, payload: Vec<u8>| {
+        Message::new(
+            topic.as_str(),
+            partition.unwrap_or_default(),
+            0,
+            args.timestamp.map(Timestamp::CreateTime),
+            headers.clone(),
+            key,
+            Some(payload),
+        )
+    };
+
+    eprintln!("[*] connecting to kafka brokers: {}", brokers.join(", "));
+    let mut sink = Kafka::new(
+        brokers,
+        topic.clone(),
+        partition,
+        &args.kafka_args,
+        &ProduceOpts::default(),
+    )?;
+
+    let key = args.key.clone().map(String::into_bytes);
+    match (&args.payload, &args.file) {
+        (Some(v), _) => write(&mut sink, &new_message(key, v.clone().into_bytes()))?,
+        (None, Some(path)) => {
+            let payload = std::fs::read(path)
+                .with_context(|| format!("failed to read payload from {}", path.display()))?;
+            write(&mut sink, &new_message(key, payload))?
+        }
+        (None, None) => {
+            eprintln!("[*] reading messages from stdin");
+            for line in stdin().lock().lines() {
+                let line = line.context("failed to read from stdin")?;
+
+                let (key, payload) =
+                    match args.delimiter.as_deref().and_then(|d| line.split_once(d)) {
+                        Some((k, v)) => (Some(k.as_bytes().to_vec()), v.as_bytes().to_vec()),
+                        None => (key.clone(), line.into_bytes()),
+                    };
+
+                write(&mut sink, &new_message(key, payload))?;
+            }
+        }
+    }
+
+    sink.flush()?;
+
+    let report = sink.delivery_report().unwrap_or_default();
+    for ((topic, partition), offsets) in &report.offsets {
+        if offsets.start() == offsets.end() {
+            eprintln!("[+] {}/{}: offset {}", topic, partition, offsets.start());
+        } else {
+            eprintln!(
+                "[+] {}/{}: offsets {} to {}",
+                topic,
+                partition,
+                offsets.start(),
+                offsets.end()
+            );
+        }
+    }
+    for (err, n) in &report.failed {
+        eprintln!("[-] {} messages failed delivery: {}", n, err);
+    }
+
+    if report.failed_count() > 0 {
+        return Err(anyhow!(
+            "{} of {} messages failed delivery",
+            report.failed_count(),
+            report.failed_count() + report.delivered
+        ));
+    }
+
+    eprintln!("[+] complete - delivered {} messages", report.delivered);
+
+    Ok(())
+}
+
+/// Write `msg` to `sink`, retrying any non-fatal errors such as a full
+/// producer queue.
+fn write(sink: &mut Kafka, msg: &Message) -> anyhow::Result<()> {
+    loop {
+        match sink.write(msg) {
+            Ok(_) => return Ok(()),
+            Err(e) if e.is::<FatalError>() => return Err(e),
+            Err(e) => eprintln!("[-] write error: {}", e),
+        }
+        std::thread::sleep(Duration::from_millis(500));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::common::test_util::test_parse;
+
+    use super::*;
+
+    test_parse!(
+        ok,
+        type = Header,
+        input = "type=order",
+        want = Ok(Header { name, value }) if name == "type" && value == "order"
+    );
+
+    test_parse!(
+        value_with_equals,
+        type = Header,
+        input = "query=a=b",
+        want = Ok(Header { name, value }) if name == "query" && value == "a=b"
+    );
+
+    test_parse!(
+        empty_value,
+        type = Header,
+        input = "flag=",
+        want = Ok(Header { name, value }) if name == "flag" && value.is_empty()
+    );
+
+    test_parse!(no_equals, type = Header, input = "type", want = Err(HeaderError::Format));
+
+    test_parse!(no_name, type = Header, input = "=order", want = Err(HeaderError::Format));
+}
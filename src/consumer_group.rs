@@ -0,0 +1,974 @@
+//! Consumer group inspection, describing the members of a group, the
+//! partitions assigned to them, and how far behind the group is, and
+//! repositioning, exporting and restoring the committed offsets of inactive
+//! groups.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    num::ParseIntError,
+    str::FromStr,
+};
+
+use anyhow::Context;
+use rdkafka::{
+    config::FromClientConfig,
+    consumer::{BaseConsumer, Consumer},
+    groups::{GroupInfo, GroupList},
+    metadata::Metadata,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    cli::common::KafkaOpts,
+    source::kafka::{
+        commit_group_offsets, committed_offsets, high_watermarks, message_timestamps,
+        offsets_for_time, offsets_for_times, readable_end_offsets,
+    },
+};
+
+/// The protocol type of groups formed by Kafka consumers, the only type with a
+/// known assignment format.
+const CONSUMER_PROTOCOL_TYPE: &str = "consumer";
+
+/// A consumer group, as returned when listing all groups.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GroupSummary {
+    pub name: String,
+    pub state: String,
+    pub protocol_type: String,
+
+    /// The number of active members.
+    pub members: usize,
+}
+
+/// A consumer group, its members, and the offsets it has committed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GroupDescription {
+    pub name: String,
+    pub state: String,
+    pub protocol_type: String,
+    pub protocol: String,
+    pub members: Vec<Member>,
+
+    /// The committed offset and lag of every partition either assigned to a
+    /// member or with an offset committed by the group.
+    pub offsets: Vec<PartitionLag>,
+}
+
+impl GroupDescription {
+    /// The sum of the lag of all partitions with a committed offset.
+    pub fn total_lag(&self) -> i64 {
+        self.offsets.iter().filter_map(|v| v.lag).sum()
+    }
+
+    /// Return the member assigned `(topic, partition)`, if any.
+    pub fn assignee(&self, topic: &str, partition: i32) -> Option<&Member> {
+        self.members.iter().find(|m| {
+            m.assignment
+                .iter()
+                .any(|tp| tp.topic == topic && tp.partition == partition)
+        })
+    }
+}
+
+/// An active member of a consumer group.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Member {
+    pub id: String,
+    pub client_id: String,
+    pub host: String,
+    pub assignment: Vec<TopicPartition>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct TopicPartition {
+    pub topic: String,
+    pub partition: i32,
+}
+
+/// The progress of a consumer group through a partition.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PartitionLag {
+    pub topic: String,
+    pub partition: i32,
+
+    /// The next offset the group will consume, if it has committed one.
+    pub committed: Option<i64>,
+
+    /// The high watermark of the partition.
+    pub high: i64,
+
+    /// The number of offsets between the committed offset and the high
+    /// watermark.
+    pub lag: Option<i64>,
+}
+
+/// List all consumer groups known to the cluster.
+pub fn list(brokers: Vec<String>, kafka_opts: &KafkaOpts) -> anyhow::Result<Vec<GroupSummary>> {
+    let consumer = BaseConsumer::from_config(&kafka_opts.new_consumer_config(brokers))
+        .context("failed to initialise kafka consumer")?;
+
+    let (_, groups) = fetch_groups(&consumer, None, kafka_opts)?;
+
+    let mut out = groups
+        .groups()
+        .iter()
+        .map(|g| GroupSummary {
+            name: g.name().to_string(),
+            state: g.state().to_string(),
+            protocol_type: g.protocol_type().to_string(),
+            members: g.members().len(),
+        })
+        .collect::<Vec<_>>();
+    out.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(out)
+}
+
+/// Describe the members, assignments, committed offsets and lag of `group`.
+pub fn describe(
+    brokers: Vec<String>,
+    group: &str,
+    kafka_opts: &KafkaOpts,
+) -> anyhow::Result<GroupDescription> {
+    let consumer = BaseConsumer::from_config(&kafka_opts.new_consumer_config(brokers.clone()))
+        .context("failed to initialise kafka consumer")?;
+
+    let (meta, groups) = fetch_groups(&consumer, Some(group), kafka_opts)?;
+    let info = groups
+        .groups()
+        .iter()
+        .find(|g| g.name() == group)
+        .ok_or_else(|| anyhow::anyhow!("consumer group {} not found", group))?;
+
+    let members = members(info)?;
+
+    // Offsets can be committed for partitions not currently assigned to any
+    // member (or by a group with no members at all), so query every
+    // partition in the cluster.
+    let assigned = members
+        .iter()
+        .flat_map(|m| {
+            m.assignment
+                .iter()
+                .map(|tp| (tp.topic.clone(), tp.partition))
+        })
+        .collect::<BTreeSet<_>>();
+    let partitions = meta
+        .topics()
+        .iter()
+        .filter(|t| !t.name().starts_with("__"))
+        .flat_map(|t| {
+            t.partitions()
+                .iter()
+                .map(|p| (t.name().to_string(), p.id()))
+        })
+        .chain(assigned.iter().cloned())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    let committed = committed_offsets(&brokers, group, &partitions, kafka_opts)?
+        .into_iter()
+        .filter(|(tp, offset)| offset.is_some() || assigned.contains(tp))
+        .collect::<BTreeMap<_, _>>();
+
+    if info.state() == "Dead" && committed.is_empty() {
+        return Err(anyhow::anyhow!("consumer group {} not found", group));
+    }
+
+    let described = committed.keys().cloned().collect::<Vec<_>>();
+    let mut high = high_watermarks(brokers, &described, kafka_opts)?;
+
+    let offsets = committed
+        .into_iter()
+        .map(|(tp, committed)| {
+            let high = high.remove(&tp).unwrap_or_default();
+            PartitionLag {
+                lag: committed.map(|v| (high - v).max(0)),
+                topic: tp.0,
+                partition: tp.1,
+                committed,
+                high,
+            }
+        })
+        .collect();
+
+    Ok(GroupDescription {
+        name: info.name().to_string(),
+        state: info.state().to_string(),
+        protocol_type: info.protocol_type().to_string(),
+        protocol: info.protocol().to_string(),
+        members,
+        offsets,
+    })
+}
+
+/// Return the number of active members of `group`.
+pub fn active_members(
+    brokers: Vec<String>,
+    group: &str,
+    kafka_opts: &KafkaOpts,
+) -> anyhow::Result<usize> {
+    let consumer = BaseConsumer::from_config(&kafka_opts.new_consumer_config(brokers))
+        .context("failed to initialise kafka consumer")?;
+
+    let (_, groups) = fetch_groups(&consumer, Some(group), kafka_opts)?;
+
+    Ok(groups
+        .groups()
+        .iter()
+        .filter(|g| g.name() == group)
+        .map(|g| g.members().len())
+        .sum())
+}
+
+/// Selects all partitions of a topic ("orders"), or a single partition
+/// ("orders/3").
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionSelector {
+    pub topic: String,
+    pub partition: Option<i32>,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum SelectorError {
+    #[error("topic name cannot be empty")]
+    EmptyTopic,
+
+    #[error("invalid partition number: {0}")]
+    Partition(ParseIntError),
+}
+
+impl FromStr for PartitionSelector {
+    type Err = SelectorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (topic, partition) = match s.split_once('/') {
+            Some((topic, partition)) => (
+                topic,
+                Some(partition.parse().map_err(SelectorError::Partition)?),
+            ),
+            None => (s, None),
+        };
+
+        if topic.is_empty() {
+            return Err(SelectorError::EmptyTopic);
+        }
+
+        Ok(Self {
+            topic: topic.to_string(),
+            partition,
+        })
+    }
+}
+
+/// How the new committed offset of each partition is chosen by
+/// [`plan_reset`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetStrategy {
+    /// The first message at or after the timestamp (milliseconds since
+    /// epoch), or the high watermark if there is none.
+    ToTimestamp(i64),
+
+    /// An explicit offset.
+    ToOffset(i64),
+
+    /// The committed offset moved forwards (or backwards, if negative) by
+    /// this many offsets.
+    ShiftBy(i64),
+
+    /// The high watermark, skipping all existing messages.
+    ToLatest,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ResetError {
+    #[error("no committed offset to shift")]
+    NoCommittedOffset,
+}
+
+impl ResetStrategy {
+    /// Resolve the new offset of a partition with the watermarks `low` and
+    /// `high`, clamping it to within the watermarks.
+    ///
+    /// `at` is the offset resolved for [`ResetStrategy::ToTimestamp`].
+    fn resolve(
+        &self,
+        current: Option<i64>,
+        low: i64,
+        high: i64,
+        at: Option<i64>,
+    ) -> Result<i64, ResetError> {
+        let offset = match *self {
+            ResetStrategy::ToTimestamp(_) => at.unwrap_or(high),
+            ResetStrategy::ToOffset(v) => v,
+            ResetStrategy::ShiftBy(n) => current.ok_or(ResetError::NoCommittedOffset)? + n,
+            ResetStrategy::ToLatest => high,
+        };
+
+        Ok(offset.clamp(low, high))
+    }
+}
+
+/// A planned change to the committed offset of a partition.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OffsetChange {
+    pub topic: String,
+    pub partition: i32,
+
+    /// The offset currently committed, if any.
+    pub current: Option<i64>,
+
+    /// The offset that will be committed.
+    pub new: i64,
+}
+
+/// Resolve the new committed offset of `group` for each partition selected by
+/// `selection` using `strategy`, without modifying the group.
+///
+/// If `selection` is empty, every partition with an offset committed by the
+/// group is selected.
+pub fn plan_reset(
+    brokers: Vec<String>,
+    group: &str,
+    selection: &[PartitionSelector],
+    strategy: ResetStrategy,
+    kafka_opts: &KafkaOpts,
+) -> anyhow::Result<Vec<OffsetChange>> {
+    let consumer = BaseConsumer::from_config(&kafka_opts.new_consumer_config(brokers.clone()))
+        .context("failed to initialise kafka consumer")?;
+
+    let meta = consumer
+        .fetch_metadata(None, kafka_opts.timeout)
+        .context("failed to read cluster metadata")?;
+
+    let mut partitions = BTreeSet::new();
+    for s in selection {
+        let topic = meta
+            .topics()
+            .iter()
+            .find(|t| t.name() == s.topic)
+            .ok_or_else(|| anyhow::anyhow!("topic {} not found", s.topic))?;
+
+        let ids = topic.partitions().iter().map(|p| p.id());
+        match s.partition {
+            Some(p) if ids.clone().any(|v| v == p) => {
+                partitions.insert((s.topic.clone(), p));
+            }
+            Some(p) => return Err(anyhow::anyhow!("partition {}/{} not found", s.topic, p)),
+            None => partitions.extend(ids.map(|p| (s.topic.clone(), p))),
+        }
+    }
+
+    let committed = if selection.is_empty() {
+        let all = meta
+            .topics()
+            .iter()
+            .filter(|t| !t.name().starts_with("__"))
+            .flat_map(|t| {
+                t.partitions()
+                    .iter()
+                    .map(|p| (t.name().to_string(), p.id()))
+            })
+            .collect::<Vec<_>>();
+
+        let committed = committed_offsets(&brokers, group, &all, kafka_opts)?
+            .into_iter()
+            .filter(|(_, v)| v.is_some())
+            .collect::<BTreeMap<_, _>>();
+        if committed.is_empty() {
+            return Err(anyhow::anyhow!(
+                "consumer group {} has no committed offsets, select partitions with --topic",
+                group
+            ));
+        }
+        committed
+    } else {
+        let partitions = partitions.into_iter().collect::<Vec<_>>();
+        committed_offsets(&brokers, group, &partitions, kafka_opts)?
+    };
+
+    let mut at = match strategy {
+        ResetStrategy::ToTimestamp(ts) => {
+            let partitions = committed.keys().cloned().collect::<Vec<_>>();
+            offsets_for_time(brokers, &partitions, ts, kafka_opts)?
+        }
+        _ => BTreeMap::new(),
+    };
+
+    let mut changes = Vec::with_capacity(committed.len());
+    for ((topic, partition), current) in committed {
+        let (low, high) = consumer
+            .fetch_watermarks(&topic, partition, kafka_opts.timeout)
+            .with_context(|| format!("failed to read watermarks for {}/{}", topic, partition))?;
+
+        let at = at.remove(&(topic.clone(), partition)).flatten();
+        let new = strategy
+            .resolve(current, low, high, at)
+            .with_context(|| format!("cannot reset {}/{}", topic, partition))?;
+
+        changes.push(OffsetChange {
+            topic,
+            partition,
+            current,
+            new,
+        });
+    }
+
+    Ok(changes)
+}
+
+/// Commit the new offsets in `changes` on behalf of `group`.
+///
+/// All offsets are committed in a single request, so either every partition
+/// is repositioned or none are. The broker rejects the commit if the group
+/// has active members.
+pub fn apply_reset(
+    brokers: Vec<String>,
+    group: &str,
+    changes: &[OffsetChange],
+    kafka_opts: &KafkaOpts,
+) -> anyhow::Result<()> {
+    commit_group_offsets(
+        brokers,
+        group,
+        kafka_opts,
+        changes
+            .iter()
+            .map(|c| ((c.topic.clone(), c.partition), c.new)),
+    )
+}
+
+/// The committed offsets of a set of consumer groups, as written by
+/// [`export`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OffsetSnapshot {
+    pub groups: Vec<GroupOffsets>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupOffsets {
+    pub group: String,
+    pub offsets: Vec<CommittedOffset>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommittedOffset {
+    pub topic: String,
+    pub partition: i32,
+
+    /// The next offset the group will consume.
+    pub offset: i64,
+
+    /// True if the group had consumed every message in the partition, with
+    /// `offset` at the high watermark.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub at_end: bool,
+
+    /// The timestamp of the message at `offset`, used to translate the offset
+    /// to another cluster. Absent if the group is `at_end`, or the message has
+    /// no timestamp.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<i64>,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ImportError {
+    #[error("no timestamp recorded to translate the offset with")]
+    NoTimestamp,
+}
+
+impl CommittedOffset {
+    /// How the offset is restored, either as-is or by its timestamp if
+    /// `translate` is true.
+    fn import_strategy(&self, translate: bool) -> Result<ResetStrategy, ImportError> {
+        Ok(match (translate, self.at_end, self.timestamp) {
+            (false, _, _) => ResetStrategy::ToOffset(self.offset),
+            (true, true, _) => ResetStrategy::ToLatest,
+            (true, false, Some(ts)) => ResetStrategy::ToTimestamp(ts),
+            (true, false, None) => return Err(ImportError::NoTimestamp),
+        })
+    }
+}
+
+/// Snapshot the committed offsets of `groups`, or of every group in the
+/// cluster if empty.
+///
+/// Groups without any committed offsets are omitted.
+pub fn export(
+    brokers: Vec<String>,
+    groups: &[String],
+    kafka_opts: &KafkaOpts,
+) -> anyhow::Result<OffsetSnapshot> {
+    let consumer = BaseConsumer::from_config(&kafka_opts.new_consumer_config(brokers.clone()))
+        .context("failed to initialise kafka consumer")?;
+
+    let (meta, list) = fetch_groups(&consumer, None, kafka_opts)?;
+
+    let names = if groups.is_empty() {
+        let mut names = list
+            .groups()
+            .iter()
+            .map(|g| g.name().to_string())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    } else {
+        groups.to_vec()
+    };
+
+    let partitions = meta
+        .topics()
+        .iter()
+        .filter(|t| !t.name().starts_with("__"))
+        .flat_map(|t| {
+            t.partitions()
+                .iter()
+                .map(|p| (t.name().to_string(), p.id()))
+        })
+        .collect::<Vec<_>>();
+
+    let mut out = Vec::with_capacity(names.len());
+    for group in names {
+        let committed = committed_offsets(&brokers, &group, &partitions, kafka_opts)?
+            .into_iter()
+            .filter_map(|(tp, offset)| Some((tp, offset?)))
+            .collect::<Vec<_>>();
+        if committed.is_empty() {
+            continue;
+        }
+
+        let committed_partitions = committed
+            .iter()
+            .map(|(tp, _)| tp.clone())
+            .collect::<Vec<_>>();
+        let end = readable_end_offsets(brokers.clone(), &committed_partitions, kafka_opts)?;
+
+        let at_end = |tp: &(String, i32), offset: i64| end.get(tp).is_none_or(|v| offset >= *v);
+        let readable = committed
+            .iter()
+            .filter(|(tp, offset)| !at_end(tp, *offset))
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut timestamps = message_timestamps(brokers.clone(), &readable, kafka_opts)?;
+
+        let offsets = committed
+            .into_iter()
+            .map(|(tp, offset)| {
+                let at_end = at_end(&tp, offset);
+                let timestamp = match timestamps.remove(&tp) {
+                    Some(v) => v,
+                    None if at_end => None,
+                    None => {
+                        return Err(anyhow::anyhow!(
+                            "failed to read the message at offset {} of {}/{} for group {}",
+                            offset,
+                            tp.0,
+                            tp.1,
+                            group
+                        ))
+                    }
+                };
+
+                Ok(CommittedOffset {
+                    topic: tp.0,
+                    partition: tp.1,
+                    offset,
+                    at_end,
+                    timestamp,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        out.push(GroupOffsets { group, offsets });
+    }
+
+    Ok(OffsetSnapshot { groups: out })
+}
+
+/// Resolve the offsets to commit to restore `snapshot` onto the cluster at
+/// `brokers`, without modifying the group.
+///
+/// If `translate` is true, each partition is positioned at the first message
+/// at or after the timestamp recorded in the snapshot (or the end of the
+/// partition if the group had consumed every message), rather than at the
+/// recorded offset. Offsets are clamped to the watermarks of each partition.
+pub fn plan_import(
+    brokers: Vec<String>,
+    snapshot: &GroupOffsets,
+    translate: bool,
+    kafka_opts: &KafkaOpts,
+) -> anyhow::Result<Vec<OffsetChange>> {
+    let consumer = BaseConsumer::from_config(&kafka_opts.new_consumer_config(brokers.clone()))
+        .context("failed to initialise kafka consumer")?;
+
+    let partitions = snapshot
+        .offsets
+        .iter()
+        .map(|v| (v.topic.clone(), v.partition))
+        .collect::<Vec<_>>();
+    let committed = committed_offsets(&brokers, &snapshot.group, &partitions, kafka_opts)?;
+
+    let mut at = if translate {
+        offsets_for_times(
+            brokers,
+            snapshot
+                .offsets
+                .iter()
+                .filter_map(|v| Some(((v.topic.clone(), v.partition), v.timestamp?))),
+            kafka_opts,
+        )?
+    } else {
+        BTreeMap::new()
+    };
+
+    let mut changes = Vec::with_capacity(snapshot.offsets.len());
+    for v in &snapshot.offsets {
+        let (low, high) = consumer
+            .fetch_watermarks(&v.topic, v.partition, kafka_opts.timeout)
+            .with_context(|| {
+                format!("failed to read watermarks for {}/{}", v.topic, v.partition)
+            })?;
+
+        let strategy = v.import_strategy(translate).with_context(|| {
+            format!(
+                "cannot restore {}/{} of group {}",
+                v.topic, v.partition, snapshot.group
+            )
+        })?;
+        let tp = (v.topic.clone(), v.partition);
+        let current = committed.get(&tp).copied().flatten();
+        let new = strategy
+            .resolve(current, low, high, at.remove(&tp).flatten())
+            .with_context(|| format!("cannot restore {}/{}", v.topic, v.partition))?;
+
+        changes.push(OffsetChange {
+            topic: v.topic.clone(),
+            partition: v.partition,
+            current,
+            new,
+        });
+    }
+
+    Ok(changes)
+}
+
+/// Read the cluster metadata, and the list of all groups (or only `group`).
+///
+/// The metadata is always fetched first: librdkafka busy-waits without
+/// honouring the timeout if groups are listed before any metadata has been
+/// received, such as when the brokers are unreachable.
+fn fetch_groups(
+    consumer: &BaseConsumer,
+    group: Option<&str>,
+    kafka_opts: &KafkaOpts,
+) -> anyhow::Result<(Metadata, GroupList)> {
+    let meta = consumer
+        .fetch_metadata(None, kafka_opts.timeout)
+        .context("failed to read cluster metadata")?;
+
+    let groups = consumer
+        .fetch_group_list(group, kafka_opts.timeout)
+        .context("failed to list consumer groups")?;
+
+    Ok((meta, groups))
+}
+
+/// Read the members of `info`, decoding their partition assignments if the
+/// group was formed by Kafka consumers.
+fn members(info: &GroupInfo) -> anyhow::Result<Vec<Member>> {
+    info.members()
+        .iter()
+        .map(|m| {
+            let assignment = match (info.protocol_type(), m.assignment()) {
+                (CONSUMER_PROTOCOL_TYPE, Some(v)) if !v.is_empty() => decode_assignment(v)
+                    .with_context(|| format!("invalid assignment for member {}", m.id()))?,
+                _ => vec![],
+            };
+
+            Ok(Member {
+                id: m.id().to_string(),
+                client_id: m.client_id().to_string(),
+                host: m.client_host().to_string(),
+                assignment,
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum AssignmentError {
+    #[error("assignment truncated")]
+    Truncated,
+
+    #[error("invalid topic name encoding")]
+    TopicName,
+}
+
+/// Decode a consumer protocol assignment, returning the assigned partitions.
+///
+/// The assignment is encoded as a version (int16) followed by an array
+/// (int32 length) of topics, each a name (int16 length string) and an array
+/// of partitions (int32). Any trailing user data is ignored.
+fn decode_assignment(buf: &[u8]) -> Result<Vec<TopicPartition>, AssignmentError> {
+    let mut r = Reader(buf);
+
+    let _version = r.i16()?;
+
+    let mut out = Vec::new();
+    for _ in 0..r.i32()?.max(0) {
+        let len = r.i16()?.max(0) as usize;
+        let topic =
+            String::from_utf8(r.take(len)?.to_vec()).map_err(|_| AssignmentError::TopicName)?;
+
+        for _ in 0..r.i32()?.max(0) {
+            out.push(TopicPartition {
+                topic: topic.clone(),
+                partition: r.i32()?,
+            });
+        }
+    }
+
+    out.sort();
+    Ok(out)
+}
+
+/// A cursor over big-endian encoded protocol fields.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], AssignmentError> {
+        if self.0.len() < n {
+            return Err(AssignmentError::Truncated);
+        }
+        let (v, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(v)
+    }
+
+    fn i16(&mut self) -> Result<i16, AssignmentError> {
+        Ok(i16::from_be_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn i32(&mut self) -> Result<i32, AssignmentError> {
+        Ok(i32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encode an assignment of `topics` in the consumer protocol format.
+    fn encode(topics: &[(&str, &[i32])], user_data: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(1_i16.to_be_bytes());
+        buf.extend((topics.len() as i32).to_be_bytes());
+        for (topic, partitions) in topics {
+            buf.extend((topic.len() as i16).to_be_bytes());
+            buf.extend(topic.as_bytes());
+            buf.extend((partitions.len() as i32).to_be_bytes());
+            for p in *partitions {
+                buf.extend(p.to_be_bytes());
+            }
+        }
+        buf.extend((user_data.len() as i32).to_be_bytes());
+        buf.extend(user_data);
+        buf
+    }
+
+    fn tp(topic: &str, partition: i32) -> TopicPartition {
+        TopicPartition {
+            topic: topic.to_string(),
+            partition,
+        }
+    }
+
+    #[test]
+    fn test_decode_assignment() {
+        let buf = encode(&[("orders", &[2, 0]), ("payments", &[1])], b"bananas");
+
+        assert_eq!(
+            decode_assignment(&buf),
+            Ok(vec![tp("orders", 0), tp("orders", 2), tp("payments", 1)])
+        );
+    }
+
+    #[test]
+    fn test_decode_assignment_empty() {
+        assert_eq!(decode_assignment(&encode(&[], &[])), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_decode_assignment_truncated() {
+        let buf = encode(&[("orders", &[0, 1])], &[]);
+
+        // Drop the user data and the last partition.
+        assert_eq!(
+            decode_assignment(&buf[..buf.len() - 6]),
+            Err(AssignmentError::Truncated)
+        );
+    }
+
+    #[test]
+    fn test_parse_selector() {
+        assert_eq!(
+            "orders".parse::<PartitionSelector>(),
+            Ok(PartitionSelector {
+                topic: "orders".to_string(),
+                partition: None,
+            })
+        );
+        assert_eq!(
+            "orders/3".parse::<PartitionSelector>(),
+            Ok(PartitionSelector {
+                topic: "orders".to_string(),
+                partition: Some(3),
+            })
+        );
+        assert!(matches!(
+            "orders/x".parse::<PartitionSelector>(),
+            Err(SelectorError::Partition(_))
+        ));
+        assert_eq!(
+            "/3".parse::<PartitionSelector>(),
+            Err(SelectorError::EmptyTopic)
+        );
+    }
+
+    #[test]
+    fn test_resolve_reset() {
+        use ResetStrategy::*;
+
+        // Watermarks of 10 and 100, with 42 committed.
+        let resolve = |s: ResetStrategy, at| s.resolve(Some(42), 10, 100, at);
+
+        assert_eq!(resolve(ToTimestamp(1), Some(50)), Ok(50));
+        assert_eq!(resolve(ToTimestamp(1), None), Ok(100));
+        assert_eq!(resolve(ToOffset(20), None), Ok(20));
+        assert_eq!(resolve(ShiftBy(1), None), Ok(43));
+        assert_eq!(resolve(ShiftBy(-2), None), Ok(40));
+        assert_eq!(resolve(ToLatest, None), Ok(100));
+
+        // Out of range offsets are clamped to the watermarks.
+        assert_eq!(resolve(ToOffset(0), None), Ok(10));
+        assert_eq!(resolve(ToOffset(1000), None), Ok(100));
+        assert_eq!(resolve(ShiftBy(-100), None), Ok(10));
+
+        assert_eq!(
+            ShiftBy(1).resolve(None, 10, 100, None),
+            Err(ResetError::NoCommittedOffset)
+        );
+        assert_eq!(ToOffset(20).resolve(None, 10, 100, None), Ok(20));
+    }
+
+    #[test]
+    fn test_snapshot_json() {
+        let snapshot = OffsetSnapshot {
+            groups: vec![GroupOffsets {
+                group: "bananas".to_string(),
+                offsets: vec![
+                    CommittedOffset {
+                        topic: "orders".to_string(),
+                        partition: 0,
+                        offset: 42,
+                        at_end: false,
+                        timestamp: Some(1663602628526),
+                    },
+                    CommittedOffset {
+                        topic: "orders".to_string(),
+                        partition: 1,
+                        offset: 7,
+                        at_end: true,
+                        timestamp: None,
+                    },
+                ],
+            }],
+        };
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert_eq!(
+            json,
+            r#"{"groups":[{"group":"bananas","offsets":[{"topic":"orders","partition":0,"offset":42,"timestamp":1663602628526},{"topic":"orders","partition":1,"offset":7,"at_end":true}]}]}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<OffsetSnapshot>(&json).unwrap(),
+            snapshot
+        );
+    }
+
+    #[test]
+    fn test_import_strategy() {
+        let offset = |at_end, timestamp| CommittedOffset {
+            topic: "orders".to_string(),
+            partition: 0,
+            offset: 42,
+            at_end,
+            timestamp,
+        };
+
+        assert_eq!(
+            offset(false, Some(1000)).import_strategy(false),
+            Ok(ResetStrategy::ToOffset(42))
+        );
+        assert_eq!(
+            offset(false, Some(1000)).import_strategy(true),
+            Ok(ResetStrategy::ToTimestamp(1000))
+        );
+        assert_eq!(
+            offset(true, None).import_strategy(true),
+            Ok(ResetStrategy::ToLatest)
+        );
+
+        // A missing timestamp is never mistaken for a consumed partition.
+        assert_eq!(
+            offset(false, None).import_strategy(true),
+            Err(ImportError::NoTimestamp)
+        );
+        assert_eq!(
+            offset(false, None).import_strategy(false),
+            Ok(ResetStrategy::ToOffset(42))
+        );
+    }
+
+    #[test]
+    fn test_lag() {
+        let group = GroupDescription {
+            name: "bananas".to_string(),
+            state: "Stable".to_string(),
+            protocol_type: "consumer".to_string(),
+            protocol: "range".to_string(),
+            members: vec![Member {
+                id: "m-1".to_string(),
+                client_id: "rdkafka".to_string(),
+                host: "/10.0.0.1".to_string(),
+                assignment: vec![tp("orders", 1)],
+            }],
+            offsets: vec![
+                PartitionLag {
+                    topic: "orders".to_string(),
+                    partition: 0,
+                    committed: Some(10),
+                    high: 42,
+                    lag: Some(32),
+                },
+                PartitionLag {
+                    topic: "orders".to_string(),
+                    partition: 1,
+                    committed: None,
+                    high: 5,
+                    lag: None,
+                },
+            ],
+        };
+
+        assert_eq!(group.total_lag(), 32);
+        assert_eq!(
+            group.assignee("orders", 1).map(|m| m.id.as_str()),
+            Some("m-1")
+        );
+        assert_eq!(group.assignee("orders", 0), None);
+    }
+}
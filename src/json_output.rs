@@ -1,13 +1,20 @@
-//! JSON representation of a [`Message`], used in CLI output.
+//! JSON representation of a [`Message`], used in CLI output and by the NDJSON
+//! source and sink.
 
-use std::collections::BTreeMap;
+use std::{borrow::Cow, collections::BTreeMap};
 
 use crate::message::{Message, Timestamp};
 
-use serde::{Deserialize, Serialize, Serializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 #[derive(Debug, Deserialize, Serialize)]
-struct Base64Bytes<'m>(#[serde(serialize_with = "base64_serialise")] &'m [u8]);
+struct Base64Bytes<'m>(
+    #[serde(
+        serialize_with = "base64_serialise",
+        deserialize_with = "base64_deserialise"
+    )]
+    Cow<'m, [u8]>,
+);
 
 fn base64_serialise<S>(v: &'_ [u8], s: S) -> Result<S::Ok, S::Error>
 where
@@ -18,13 +25,30 @@ where
     s.serialize_str(&encoded)
 }
 
+fn base64_deserialise<'de, 'm, D>(d: D) -> Result<Cow<'m, [u8]>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use base64::Engine;
+    let encoded = Cow::<str>::deserialize(d)?;
+    base64::engine::general_purpose::STANDARD
+        .decode(encoded.as_bytes())
+        .map(Cow::Owned)
+        .map_err(serde::de::Error::custom)
+}
+
+/// A [`Message`] with the binary key, payload and header values base64
+/// encoded.
+///
+/// Deserialising a [`JsonMessage`] and converting it back into a [`Message`]
+/// reproduces the original message exactly.
 #[derive(Debug, Deserialize, Serialize)]
 pub struct JsonMessage<'m> {
-    topic: &'m str,
+    topic: Cow<'m, str>,
     partition: i32,
     offset: i64,
     timestamp: Option<Timestamp>,
-    headers: Option<BTreeMap<&'m str, Base64Bytes<'m>>>,
+    headers: Option<BTreeMap<Cow<'m, str>, Base64Bytes<'m>>>,
     key: Option<Base64Bytes<'m>>,
     payload: Option<Base64Bytes<'m>>,
 }
@@ -32,21 +56,39 @@ pub struct JsonMessage<'m> {
 impl<'m> From<&'m Message> for JsonMessage<'m> {
     fn from(m: &'m Message) -> Self {
         Self {
-            topic: m.topic(),
+            topic: Cow::Borrowed(m.topic()),
             partition: m.partition(),
             offset: m.offset(),
             timestamp: m.timestamp().cloned(),
             headers: m.headers().map(|v| {
                 v.iter()
-                    .map(|(k, v)| (k.as_str(), Base64Bytes(v)))
+                    .map(|(k, v)| (Cow::Borrowed(k.as_str()), Base64Bytes(Cow::Borrowed(v))))
                     .collect()
             }),
-            key: m.key().map(Base64Bytes),
-            payload: m.payload().map(Base64Bytes),
+            key: m.key().map(|v| Base64Bytes(Cow::Borrowed(v))),
+            payload: m.payload().map(|v| Base64Bytes(Cow::Borrowed(v))),
         }
     }
 }
 
+impl From<JsonMessage<'_>> for Message {
+    fn from(m: JsonMessage<'_>) -> Self {
+        Message::new(
+            m.topic.into_owned(),
+            m.partition,
+            m.offset,
+            m.timestamp,
+            m.headers.map(|v| {
+                v.into_iter()
+                    .map(|(k, v)| (k.into_owned(), v.0.into_owned()))
+                    .collect()
+            }),
+            m.key.map(|v| v.0.into_owned()),
+            m.payload.map(|v| v.0.into_owned()),
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -67,6 +109,11 @@ mod tests {
 
 					let want = $want;
                     assert_eq!(got, $want, "got:\n{got}\nwant:\n{want}");
+
+                    // The JSON representation decodes to the original message.
+                    let decoded = serde_json::from_str::<JsonMessage>(&got)
+                        .expect("failed to deserialise message");
+                    assert_eq!(Message::from(decoded), msg);
                 }
             }
         };
@@ -159,6 +206,12 @@ mod tests {
 }"#
     );
 
+    #[test]
+    fn test_json_invalid_base64() {
+        let input = r#"{"topic":"bananas","partition":0,"offset":0,"timestamp":null,"headers":null,"key":"!!","payload":null}"#;
+        assert!(serde_json::from_str::<JsonMessage>(input).is_err());
+    }
+
     fn headers<T, K, V>(input: T) -> BTreeMap<String, Vec<u8>>
     where
         T: IntoIterator<Item = (K, V)>,
@@ -1,6 +1,8 @@
 pub mod cli;
+pub mod consumer_group;
 pub mod file_codec;
 pub mod json_output;
 pub mod message;
 pub mod sink;
 pub mod source;
+pub mod topic_config;
@@ -8,19 +8,22 @@ struct Cli {
 
 #[derive(Debug, Subcommand)]
 enum Command {
-    Cp(ktool::cli::cp::CliArgs),
+    Cp(Box<ktool::cli::cp::CliArgs>),
     Read(ktool::cli::read::CliArgs),
     Write(ktool::cli::write::CliArgs),
     Metadata(ktool::cli::metadata::CliArgs),
+    Offsets(ktool::cli::offsets::CliArgs),
+    Groups(ktool::cli::groups::CliArgs),
 }
 
 fn main() -> Result<(), anyhow::Error> {
     let args = Cli::parse();
     match args.command {
-        Command::Cp(v) => ktool::cli::cp::run(v),
+        Command::Cp(v) => ktool::cli::cp::run(*v),
         Command::Read(v) => ktool::cli::read::run(v),
+        Command::Write(v) => ktool::cli::write::run(v),
         Command::Metadata(v) => ktool::cli::metadata::run(v),
-        _ => unimplemented!(),
-        // Command::Write(v) => cli::write::run(v),
+        Command::Offsets(v) => ktool::cli::offsets::run(v),
+        Command::Groups(v) => ktool::cli::groups::run(v),
     }
 }
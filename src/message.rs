@@ -66,6 +66,12 @@ impl Message {
         self.headers.as_ref()
     }
 
+    /// Get a mutable reference to the message's headers, initialising them if
+    /// the message has none.
+    pub fn headers_mut(&mut self) -> &mut BTreeMap<String, Vec<u8>> {
+        self.headers.get_or_insert_with(Default::default)
+    }
+
     /// Get a reference to the message's key.
     #[must_use]
     pub fn key(&self) -> Option<&[u8]> {
@@ -77,6 +83,19 @@ impl Message {
     pub fn payload(&self) -> Option<&[u8]> {
         self.payload.as_deref()
     }
+
+    /// Get the combined size of the message key, payload and headers in bytes.
+    #[must_use]
+    pub fn size(&self) -> usize {
+        let headers = self
+            .headers
+            .iter()
+            .flatten()
+            .map(|(k, v)| k.len() + v.len())
+            .sum::<usize>();
+
+        headers + self.key.as_ref().map_or(0, Vec::len) + self.payload.as_ref().map_or(0, Vec::len)
+    }
 }
 
 impl std::fmt::Debug for Message {
@@ -120,6 +139,18 @@ pub enum Timestamp {
     LogAppendTime(i64),
 }
 
+impl Timestamp {
+    /// Get the timestamp value in milliseconds since epoch, regardless of its
+    /// type.
+    #[must_use]
+    pub fn millis(&self) -> i64 {
+        match self {
+            Timestamp::CreateTime(v) => *v,
+            Timestamp::LogAppendTime(v) => *v,
+        }
+    }
+}
+
 impl TryFrom<rdkafka::Timestamp> for Timestamp {
     type Error = ();
 
@@ -131,3 +162,57 @@ impl TryFrom<rdkafka::Timestamp> for Timestamp {
         }
     }
 }
+
+#[cfg(test)]
+pub(crate) mod test_util {
+    use super::*;
+
+    /// Build a [`Message`] for use in tests, defaulting to offset 0 of
+    /// partition 0 of "topic" with no timestamp, headers, key or payload.
+    pub(crate) fn message() -> MessageBuilder {
+        MessageBuilder(Message::new("topic", 0, 0, None, None, None, None))
+    }
+
+    pub(crate) struct MessageBuilder(Message);
+
+    impl MessageBuilder {
+        pub(crate) fn topic(mut self, topic: &str) -> Self {
+            self.0.topic = topic.to_string();
+            self
+        }
+
+        pub(crate) fn partition(mut self, partition: i32) -> Self {
+            self.0.partition = partition;
+            self
+        }
+
+        pub(crate) fn offset(mut self, offset: i64) -> Self {
+            self.0.offset = offset;
+            self
+        }
+
+        pub(crate) fn timestamp(mut self, timestamp: impl Into<Option<Timestamp>>) -> Self {
+            self.0.timestamp = timestamp.into();
+            self
+        }
+
+        pub(crate) fn header(mut self, name: &str, value: impl Into<Vec<u8>>) -> Self {
+            self.0.headers_mut().insert(name.to_string(), value.into());
+            self
+        }
+
+        pub(crate) fn key(mut self, key: impl Into<Option<Vec<u8>>>) -> Self {
+            self.0.key = key.into();
+            self
+        }
+
+        pub(crate) fn payload(mut self, payload: impl Into<Vec<u8>>) -> Self {
+            self.0.payload = Some(payload.into());
+            self
+        }
+
+        pub(crate) fn build(self) -> Message {
+            self.0
+        }
+    }
+}
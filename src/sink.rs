@@ -1,21 +1,94 @@
+pub mod dir;
 pub mod file;
 pub mod kafka;
+pub mod ndjson;
+mod partition;
+pub mod router;
+pub mod tee;
+
+use std::{collections::BTreeMap, ops::RangeInclusive};
+
+use thiserror::Error;
 
 use crate::{
-    cli::common::{KafkaOpts, Target},
+    cli::common::{KafkaOpts, ProduceOpts, Target},
     message::Message,
 };
 
-use self::{file::FileSink, kafka::Kafka};
+use self::{dir::DirSink, file::FileSink, kafka::Kafka, ndjson::NdjsonSink};
 
 // TODO: doc buffering
 
 pub trait Sink: Send {
     fn write(&mut self, msg: &Message) -> anyhow::Result<()>;
     fn flush(&mut self) -> anyhow::Result<()>;
+
+    /// Return the outcome of delivering the messages accepted by
+    /// [`Sink::write()`], for sinks that complete writes asynchronously.
+    ///
+    /// This is only complete once [`Sink::flush()`] has returned.
+    fn delivery_report(&self) -> Option<DeliveryReport> {
+        None
+    }
+}
+
+/// An error after which a [`Sink`] cannot accept any further writes.
+///
+/// Unlike other errors, retrying the failed operation never succeeds.
+#[derive(Debug, Error)]
+#[error(transparent)]
+pub struct FatalError(#[from] anyhow::Error);
+
+/// Delivery outcomes for the messages written to a [`Sink`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DeliveryReport {
+    /// The number of messages acknowledged by the destination.
+    pub delivered: u64,
+
+    /// The number of messages that were not delivered, grouped by error.
+    pub failed: BTreeMap<String, u64>,
+
+    /// The number of undelivered messages that could not be saved for retry.
+    pub unsaved: u64,
+
+    /// The range of offsets delivered to each destination topic partition.
+    pub offsets: BTreeMap<(String, i32), RangeInclusive<i64>>,
+}
+
+impl DeliveryReport {
+    /// The total number of messages that were not delivered.
+    pub fn failed_count(&self) -> u64 {
+        self.failed.values().sum()
+    }
+
+    /// Add the outcomes in `other` to this report.
+    pub fn merge(&mut self, other: DeliveryReport) {
+        self.delivered += other.delivered;
+        self.unsaved += other.unsaved;
+        for (err, n) in other.failed {
+            *self.failed.entry(err).or_default() += n;
+        }
+        for (tp, range) in other.offsets {
+            self.add_offsets(tp, range);
+        }
+    }
+
+    /// Record the delivery of `range` to the topic partition `tp`.
+    fn add_offsets(&mut self, tp: (String, i32), range: RangeInclusive<i64>) {
+        self.offsets
+            .entry(tp)
+            .and_modify(|v| {
+                *v = *v.start().min(range.start())..=*v.end().max(range.end());
+            })
+            .or_insert(range);
+    }
 }
 
-pub(crate) fn init(target: Target, kafka_opts: &KafkaOpts) -> anyhow::Result<Box<dyn Sink>> {
+pub(crate) fn init(
+    target: Target,
+    kafka_opts: &KafkaOpts,
+    produce_opts: &ProduceOpts,
+) -> anyhow::Result<Box<dyn Sink>> {
     match target {
         Target::Kafka {
             brokers,
@@ -23,11 +96,38 @@ pub(crate) fn init(target: Target, kafka_opts: &KafkaOpts) -> anyhow::Result<Box
             partition,
         } => {
             eprintln!("[*] connecting to kafka brokers: {}", brokers.join(", "));
-            Ok(Box::new(Kafka::new(brokers, topic, partition, kafka_opts)?))
+            Ok(Box::new(Kafka::new(
+                brokers,
+                topic,
+                partition,
+                kafka_opts,
+                produce_opts,
+            )?))
+        }
+        Target::KafkaPattern { .. } => Err(anyhow::anyhow!(
+            "a topic pattern cannot be used as a write destination"
+        )),
+        Target::Path(v) if v.is_dir() => {
+            eprintln!(
+                "[*] writing one file per topic to directory: {}",
+                v.display()
+            );
+            Ok(Box::new(DirSink::new(v)))
         }
         Target::Path(v) => {
             eprintln!("[*] opening file: {}", v.display());
             Ok(Box::new(FileSink::new(&v)?))
         }
+        Target::Stdio => {
+            eprintln!("[*] writing dump to stdout");
+            Ok(Box::new(FileSink::stdout()))
+        }
+        Target::Ndjson(v) => {
+            match &v {
+                Some(v) => eprintln!("[*] opening ndjson file: {}", v.display()),
+                None => eprintln!("[*] writing ndjson to stdout"),
+            }
+            Ok(Box::new(NdjsonSink::new(v.as_deref())?))
+        }
     }
 }
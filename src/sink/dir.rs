@@ -0,0 +1,44 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use crate::message::Message;
+
+use super::{file::FileSink, Sink};
+
+/// A [`Sink`] writing messages into a directory, with one file per topic named
+/// "<topic>.kbin".
+pub(crate) struct DirSink {
+    dir: PathBuf,
+    files: BTreeMap<String, FileSink>,
+}
+
+impl DirSink {
+    pub(crate) fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            files: Default::default(),
+        }
+    }
+}
+
+impl Sink for DirSink {
+    fn write(&mut self, msg: &Message) -> anyhow::Result<()> {
+        // Lazily open a file for each topic as it is first observed.
+        if !self.files.contains_key(msg.topic()) {
+            let path = self.dir.join(format!("{}.kbin", msg.topic()));
+            self.files
+                .insert(msg.topic().to_string(), FileSink::new(&path)?);
+        }
+
+        self.files
+            .get_mut(msg.topic())
+            .expect("file for topic was opened above")
+            .write(msg)
+    }
+
+    fn flush(&mut self) -> anyhow::Result<()> {
+        for f in self.files.values_mut() {
+            f.flush()?;
+        }
+        Ok(())
+    }
+}
@@ -1,6 +1,6 @@
 use std::{
-    fs::{File, OpenOptions},
-    io::{BufWriter, Write},
+    fs::OpenOptions,
+    io::{stdout, BufWriter, Write},
     path::Path,
 };
 
@@ -11,7 +11,7 @@ use crate::{file_codec, message::Message};
 use super::Sink;
 
 pub(crate) struct FileSink {
-    f: BufWriter<File>,
+    f: BufWriter<Box<dyn Write + Send>>,
 }
 
 impl FileSink {
@@ -23,9 +23,16 @@ impl FileSink {
             .with_context(|| format!("failed to open file {} for writing", path.display()))?;
 
         Ok(Self {
-            f: BufWriter::new(f),
+            f: BufWriter::new(Box::new(f)),
         })
     }
+
+    /// Stream the dump to stdout.
+    pub(crate) fn stdout() -> Self {
+        Self {
+            f: BufWriter::new(Box::new(stdout())),
+        }
+    }
 }
 
 impl Sink for FileSink {
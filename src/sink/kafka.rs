@@ -1,19 +1,30 @@
+use std::{collections::BTreeMap, path::PathBuf, sync::Mutex, time::Duration};
+
 use anyhow::Context;
 use rdkafka::{
-    config::FromClientConfig,
+    config::{FromClientConfig, FromClientConfigAndContext},
+    consumer::{BaseConsumer, Consumer, ConsumerGroupMetadata},
     message::OwnedHeaders,
-    producer::{BaseRecord, DefaultProducerContext, Producer, ThreadedProducer},
+    producer::{BaseRecord, DeliveryResult, Producer, ProducerContext, ThreadedProducer},
     util::Timeout,
+    ClientContext, Message as _, Offset, TopicPartitionList,
 };
 
-use crate::{cli::common::KafkaOpts, message::Message};
+use crate::{
+    cli::common::{KafkaOpts, Partitioner, ProduceOpts, TimestampMode},
+    message::{Message, Timestamp},
+};
 
-use super::Sink;
+use super::{file::FileSink, partition::PartitionMapper, DeliveryReport, FatalError, Sink};
 
 pub struct Kafka {
-    producer: ThreadedProducer<DefaultProducerContext>,
+    producer: ThreadedProducer<DeliveryTracker>,
     topic: String,
-    partition: Option<i32>,
+    partitions: PartitionMapper,
+    timestamps: TimestampMode,
+
+    /// The transaction state when writing with exactly-once semantics.
+    txn: Option<Transaction>,
 }
 
 impl Kafka {
@@ -22,38 +33,383 @@ impl Kafka {
         topic: String,
         partition: Option<i32>,
         kafka_opts: &KafkaOpts,
+        produce_opts: &ProduceOpts,
     ) -> anyhow::Result<Self> {
-        let config = kafka_opts.new_kafka_config(brokers);
+        let mut config = kafka_opts.new_kafka_config(brokers.clone());
 
-        let producer = ThreadedProducer::from_config(&config)
+        // Configure a transactional producer for exactly-once writes, unless
+        // the user has provided their own transactional ID.
+        if produce_opts.exactly_once && config.get("transactional.id").is_none() {
+            config.set("transactional.id", format!("ktool-{}", kafka_opts.group));
+        }
+
+        let tracker = DeliveryTracker::new(produce_opts.failed_messages.clone());
+        let producer = ThreadedProducer::from_config_and_context(&config, tracker)
             .context("failed to initialise kafka producer")?;
 
+        // A partition specified in the sink target pins all messages to it.
+        let strategy = match (partition, produce_opts.partitioner) {
+            (Some(v), Partitioner::Default) => Partitioner::Fixed(v),
+            (Some(_), _) => {
+                return Err(anyhow::anyhow!(
+                    "--partitioner cannot be used when the sink specifies a partition"
+                ))
+            }
+            (None, v) => v,
+        };
+
+        // Only look up the destination partition count when the strategy
+        // needs it, or to check a fixed partition exists in the destination
+        // topic (if the topic exists yet).
+        let count = match strategy {
+            Partitioner::Fixed(v) => match topic_partitions(&producer, &topic, kafka_opts)? {
+                Some(n) if v >= n => {
+                    return Err(anyhow::anyhow!(
+                        "partition {} does not exist in destination topic {} with {} partitions",
+                        v,
+                        topic,
+                        n
+                    ))
+                }
+                Some(n) => n,
+                None => 0,
+            },
+            v if v.needs_partition_count() => partition_count(&producer, &topic, kafka_opts)?,
+            _ => 0,
+        };
+
+        let txn = if produce_opts.exactly_once {
+            Some(Transaction::init(
+                &producer,
+                brokers,
+                kafka_opts,
+                produce_opts.transaction_size,
+            )?)
+        } else {
+            None
+        };
+
         Ok(Self {
             producer,
             topic,
-            partition,
+            partitions: PartitionMapper::new(strategy, count),
+            timestamps: produce_opts.timestamps,
+            txn,
         })
     }
 }
 
 impl Sink for Kafka {
     fn write(&mut self, msg: &crate::message::Message) -> anyhow::Result<()> {
-        let mut msg = BaseRecord::from(msg);
-        msg.topic = self.topic.as_ref();
-        msg.partition = self.partition;
+        let timestamp = self.timestamps.apply(msg.timestamp());
+        let partition = self.partitions.partition(msg)?;
+
+        // Record where the message was read from, so it can be reconstructed
+        // if delivery fails.
+        let position = Box::new(SourcePosition {
+            topic: msg.topic().to_string(),
+            partition: msg.partition(),
+            offset: msg.offset(),
+            timestamp: msg.timestamp().cloned(),
+        });
+
+        let base = BaseRecord::from(msg);
+        let record = BaseRecord {
+            topic: self.topic.as_ref(),
+            partition,
+            payload: base.payload,
+            key: base.key,
+            timestamp,
+            headers: base.headers,
+            delivery_opaque: position,
+        };
+
+        if let Some(txn) = &mut self.txn {
+            txn.begin(&self.producer)?;
+        }
 
         self.producer
-            .send(msg)
+            .send(record)
             .map_err(|(e, _)| e)
-            .context("failed to enqueue message to kafka")
+            .context("failed to enqueue message to kafka")?;
+
+        if let Some(txn) = &mut self.txn {
+            txn.add(msg);
+            if txn.is_full() {
+                txn.commit(&self.producer)?;
+            }
+        }
+
+        Ok(())
     }
 
     fn flush(&mut self) -> anyhow::Result<()> {
-        self.producer.flush(Timeout::Never);
+        // Committing a transaction flushes all messages within it.
+        match &mut self.txn {
+            Some(txn) => txn.commit(&self.producer)?,
+            None => self.producer.flush(Timeout::Never),
+        }
+
+        // All delivery reports have been processed, so any undelivered
+        // messages have been written to the retry file.
+        let mut state = self.producer.context().state.lock().unwrap();
+        if let Some(f) = state.undelivered.as_mut() {
+            f.flush()?;
+        }
+
+        Ok(())
+    }
+
+    fn delivery_report(&self) -> Option<DeliveryReport> {
+        Some(self.producer.context().state.lock().unwrap().report.clone())
+    }
+}
+
+/// An exactly-once transaction, atomically committing a batch of written
+/// messages alongside the source offsets they were read from.
+struct Transaction {
+    group: ConsumerGroupMetadata,
+    timeout: Duration,
+
+    /// The maximum number of messages in a single transaction.
+    max_len: usize,
+
+    /// True while a transaction is open.
+    open: bool,
+
+    /// The number of messages written in the open transaction.
+    len: usize,
+
+    /// The next source offset to read for each partition in the open
+    /// transaction.
+    offsets: BTreeMap<(String, i32), i64>,
+
+    /// Set once a transaction has been aborted, after which no further
+    /// transactions are started as the source and sink offsets no longer
+    /// agree.
+    aborted: bool,
+}
+
+impl Transaction {
+    fn init(
+        producer: &ThreadedProducer<DeliveryTracker>,
+        brokers: Vec<String>,
+        kafka_opts: &KafkaOpts,
+        max_len: usize,
+    ) -> anyhow::Result<Self> {
+        // The group metadata is read from a standalone consumer that never
+        // joins the group.
+        let consumer = BaseConsumer::from_config(&kafka_opts.new_consumer_config(brokers))
+            .context("failed to initialise kafka consumer")?;
+        let group = consumer
+            .group_metadata()
+            .ok_or_else(|| anyhow::anyhow!("failed to read consumer group metadata"))?;
+
+        // Fence any prior producer with the same transactional ID, aborting
+        // any transaction it left open.
+        producer
+            .init_transactions(kafka_opts.timeout)
+            .context("failed to initialise kafka transactions")?;
+
+        Ok(Self {
+            group,
+            timeout: kafka_opts.timeout,
+            max_len: max_len.max(1),
+            open: false,
+            len: 0,
+            offsets: Default::default(),
+            aborted: false,
+        })
+    }
+
+    /// Begin a transaction if one is not already open.
+    fn begin(&mut self, producer: &ThreadedProducer<DeliveryTracker>) -> anyhow::Result<()> {
+        if self.aborted {
+            return Err(FatalError(anyhow::anyhow!("a previous transaction was aborted")).into());
+        }
+
+        if !self.open {
+            producer
+                .begin_transaction()
+                .context("failed to begin transaction")
+                .map_err(FatalError)?;
+            self.open = true;
+        }
+
+        Ok(())
+    }
+
+    /// Record `msg` as written in the open transaction.
+    fn add(&mut self, msg: &Message) {
+        self.offsets
+            .insert((msg.topic().to_string(), msg.partition()), msg.offset() + 1);
+        self.len += 1;
+    }
+
+    fn is_full(&self) -> bool {
+        self.len >= self.max_len
+    }
+
+    /// Commit the open transaction (if any) alongside the source offsets of
+    /// the messages within it, aborting it upon failure.
+    fn commit(&mut self, producer: &ThreadedProducer<DeliveryTracker>) -> anyhow::Result<()> {
+        if !self.open {
+            return Ok(());
+        }
+
+        let result = (|| {
+            let mut offsets = TopicPartitionList::new();
+            for ((topic, partition), offset) in &self.offsets {
+                offsets.add_partition_offset(topic, *partition, Offset::Offset(*offset))?;
+            }
+
+            producer.send_offsets_to_transaction(&offsets, &self.group, self.timeout)?;
+            producer.commit_transaction(self.timeout)
+        })();
+
+        if let Err(e) = result {
+            self.aborted = true;
+            let _ = producer.abort_transaction(self.timeout);
+            return Err(FatalError(
+                anyhow::Error::from(e).context("failed to commit transaction (aborted)"),
+            )
+            .into());
+        }
+
+        self.open = false;
+        self.len = 0;
+        self.offsets.clear();
+
         Ok(())
     }
 }
 
+/// A producer context that records the outcome of each message delivery,
+/// optionally saving undelivered messages to a file so they can be retried.
+struct DeliveryTracker {
+    path: Option<PathBuf>,
+    state: Mutex<TrackerState>,
+}
+
+#[derive(Default)]
+struct TrackerState {
+    report: DeliveryReport,
+
+    /// The file undelivered messages are written to, opened upon the first
+    /// failure.
+    undelivered: Option<FileSink>,
+}
+
+impl DeliveryTracker {
+    fn new(path: Option<PathBuf>) -> Self {
+        Self {
+            path,
+            state: Default::default(),
+        }
+    }
+}
+
+impl ClientContext for DeliveryTracker {}
+
+/// The source location of a message being produced.
+struct SourcePosition {
+    topic: String,
+    partition: i32,
+    offset: i64,
+    timestamp: Option<Timestamp>,
+}
+
+impl ProducerContext for DeliveryTracker {
+    type DeliveryOpaque = Box<SourcePosition>;
+
+    fn delivery(&self, result: &DeliveryResult<'_>, position: Self::DeliveryOpaque) {
+        let mut state = self.state.lock().unwrap();
+
+        let (err, msg) = match result {
+            Ok(m) => {
+                state.report.delivered += 1;
+                state.report.add_offsets(
+                    (m.topic().to_string(), m.partition()),
+                    m.offset()..=m.offset(),
+                );
+                return;
+            }
+            Err(v) => v,
+        };
+
+        *state.report.failed.entry(err.to_string()).or_default() += 1;
+
+        let path = match &self.path {
+            Some(v) => v,
+            None => return,
+        };
+
+        if state.undelivered.is_none() {
+            match FileSink::new(path) {
+                Ok(f) => state.undelivered = Some(f),
+                Err(_) => {
+                    state.report.unsaved += 1;
+                    return;
+                }
+            }
+        }
+
+        // Restore the original message location, rather than that of the
+        // failed delivery.
+        let failed = Message::from(msg);
+        let msg = Message::new(
+            position.topic,
+            position.partition,
+            position.offset,
+            position.timestamp,
+            failed.headers().cloned(),
+            failed.key().map(ToOwned::to_owned),
+            failed.payload().map(ToOwned::to_owned),
+        );
+
+        let f = state.undelivered.as_mut().expect("file opened above");
+        if f.write(&msg).is_err() {
+            state.report.unsaved += 1;
+        }
+    }
+}
+
+/// Read the number of partitions in `topic` from the cluster metadata.
+fn partition_count(
+    producer: &ThreadedProducer<DeliveryTracker>,
+    topic: &str,
+    kafka_opts: &KafkaOpts,
+) -> anyhow::Result<i32> {
+    topic_partitions(producer, topic, kafka_opts)?.ok_or_else(|| {
+        anyhow::anyhow!(
+            "destination topic {} has no partitions (does it exist?)",
+            topic
+        )
+    })
+}
+
+/// Return the number of partitions in `topic`, or [`None`] if it has none
+/// (typically because it does not exist).
+fn topic_partitions(
+    producer: &ThreadedProducer<DeliveryTracker>,
+    topic: &str,
+    kafka_opts: &KafkaOpts,
+) -> anyhow::Result<Option<i32>> {
+    let meta = producer
+        .client()
+        .fetch_metadata(Some(topic), kafka_opts.timeout)
+        .context("failed to read destination topic metadata")?;
+
+    let n = meta
+        .topics()
+        .iter()
+        .find(|t| t.name() == topic)
+        .map(|t| t.partitions().len())
+        .unwrap_or_default();
+
+    Ok((n > 0).then_some(n as i32))
+}
+
 impl<'a> From<&'a Message> for BaseRecord<'a, [u8], [u8]> {
     fn from(v: &'a Message) -> Self {
         let headers = v.headers().map(|h| {
@@ -68,7 +424,7 @@ impl<'a> From<&'a Message> for BaseRecord<'a, [u8], [u8]> {
             partition: None,
             payload: v.payload(),
             key: v.key(),
-            timestamp: None,
+            timestamp: TimestampMode::Original.apply(v.timestamp()),
             headers,
             delivery_opaque: (),
         }
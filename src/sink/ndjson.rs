@@ -0,0 +1,50 @@
+use std::{
+    fs::OpenOptions,
+    io::{stdout, BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::Context;
+
+use crate::{json_output::JsonMessage, message::Message};
+
+use super::Sink;
+
+/// A [`Sink`] writing messages as newline delimited [`JsonMessage`] objects.
+pub(crate) struct NdjsonSink {
+    w: BufWriter<Box<dyn Write + Send>>,
+}
+
+impl NdjsonSink {
+    /// Write to a new file at `path`, or stdout if [`None`].
+    pub(crate) fn new(path: Option<&Path>) -> anyhow::Result<Self> {
+        let w: Box<dyn Write + Send> = match path {
+            Some(path) => Box::new(
+                OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(path)
+                    .with_context(|| {
+                        format!("failed to open file {} for writing", path.display())
+                    })?,
+            ),
+            None => Box::new(stdout()),
+        };
+
+        Ok(Self {
+            w: BufWriter::new(w),
+        })
+    }
+}
+
+impl Sink for NdjsonSink {
+    fn write(&mut self, msg: &Message) -> anyhow::Result<()> {
+        serde_json::to_writer(&mut self.w, &JsonMessage::from(msg))
+            .context("failed to write message")?;
+        self.w.write_all(b"\n").context("failed to write message")
+    }
+
+    fn flush(&mut self) -> anyhow::Result<()> {
+        self.w.flush().context("failed to flush output")
+    }
+}
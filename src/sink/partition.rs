@@ -0,0 +1,257 @@
+//! Destination partition selection for messages written to Kafka.
+
+use anyhow::anyhow;
+
+use crate::{cli::common::Partitioner, message::Message};
+
+use super::FatalError;
+
+/// Assigns each message written to a Kafka sink a destination partition,
+/// according to the configured [`Partitioner`] strategy.
+#[derive(Debug)]
+pub(crate) struct PartitionMapper {
+    strategy: Partitioner,
+
+    /// The number of partitions in the destination topic, if required by the
+    /// strategy, or 0 if unknown.
+    count: i32,
+
+    /// The next partition to use for round-robin assignment.
+    next: i32,
+}
+
+impl PartitionMapper {
+    pub(crate) fn new(strategy: Partitioner, count: i32) -> Self {
+        Self {
+            strategy,
+            count,
+            next: 0,
+        }
+    }
+
+    /// Return the destination partition for `msg`, or [`None`] to defer to
+    /// the librdkafka partitioner.
+    ///
+    /// A partition that does not exist in the destination topic is returned
+    /// as a [`FatalError`], as no retry would ever succeed.
+    pub(crate) fn partition(&mut self, msg: &Message) -> anyhow::Result<Option<i32>> {
+        let p = match self.strategy {
+            Partitioner::Default => return Ok(None),
+            Partitioner::Fixed(v) if self.count > 0 && v >= self.count => {
+                return Err(FatalError(anyhow!(
+                    "partition {} does not exist in destination topic with {} partitions",
+                    v,
+                    self.count
+                ))
+                .into())
+            }
+            Partitioner::Fixed(v) => v,
+            Partitioner::Preserve if msg.partition() >= self.count => {
+                return Err(FatalError(anyhow!(
+                    "source partition {} does not exist in destination topic with {} partitions",
+                    msg.partition(),
+                    self.count
+                ))
+                .into())
+            }
+            Partitioner::Preserve => msg.partition(),
+            Partitioner::RoundRobin => self.round_robin(),
+            Partitioner::Murmur2 => match msg.key() {
+                // Matches the Java client's Utils.toPositive(), which masks
+                // off the sign bit rather than taking the absolute value.
+                Some(key) => (murmur2(key) & 0x7fffffff) % self.count,
+                None => self.round_robin(),
+            },
+            Partitioner::Modulo => msg.partition().rem_euclid(self.count),
+        };
+
+        Ok(Some(p))
+    }
+
+    fn round_robin(&mut self) -> i32 {
+        let p = self.next;
+        self.next = (self.next + 1) % self.count;
+        p
+    }
+}
+
+/// The murmur2 hash of `data`, identical to the Java client's
+/// `org.apache.kafka.common.utils.Utils.murmur2()`.
+pub(crate) fn murmur2(data: &[u8]) -> i32 {
+    const SEED: u32 = 0x9747b28c;
+    const M: u32 = 0x5bd1e995;
+    const R: u32 = 24;
+
+    let mut h = SEED ^ data.len() as u32;
+
+    let mut chunks = data.chunks_exact(4);
+    for chunk in &mut chunks {
+        let mut k = u32::from_le_bytes(chunk.try_into().expect("chunk of 4 bytes"));
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h = h.wrapping_mul(M);
+        h ^= k;
+    }
+
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        for (i, b) in tail.iter().enumerate() {
+            h ^= (*b as u32) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+
+    h ^= h >> 13;
+    h = h.wrapping_mul(M);
+    h ^= h >> 15;
+
+    h as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::message::test_util::message;
+
+    use super::*;
+
+    macro_rules! test_murmur2 {
+        (
+			$name:ident,
+			input = $input:expr,
+			want = $want:literal
+		) => {
+            paste::paste! {
+                #[test]
+                fn [<test_murmur2_ $name>]() {
+                    let input: &[u8] = $input;
+                    assert_eq!(murmur2(input), $want);
+                }
+            }
+        };
+    }
+
+    // Test vectors from the Java client's UtilsTest.
+    test_murmur2!(short, input = b"21", want = -973932308);
+    test_murmur2!(word, input = b"foobar", want = -790332482);
+    test_murmur2!(long, input = b"a-little-bit-long-string", want = -985981536);
+    test_murmur2!(
+        longer,
+        input = b"a-little-bit-longer-string",
+        want = -1486304829
+    );
+    test_murmur2!(
+        random,
+        input = b"lkjh234lh9fiuh90y23oiuhsafujhadof229phr9h19h89h8",
+        want = -58897971
+    );
+    test_murmur2!(abc, input = b"abc", want = 479470107);
+
+    #[test]
+    fn test_default() {
+        let mut m = PartitionMapper::new(Partitioner::Default, 0);
+        assert_eq!(m.partition(&message().partition(3).build()).unwrap(), None);
+    }
+
+    #[test]
+    fn test_fixed() {
+        let mut m = PartitionMapper::new(Partitioner::Fixed(7), 0);
+        assert_eq!(
+            m.partition(&message().partition(3).build()).unwrap(),
+            Some(7)
+        );
+        assert_eq!(
+            m.partition(&message().partition(4).build()).unwrap(),
+            Some(7)
+        );
+    }
+
+    #[test]
+    fn test_fixed_out_of_range() {
+        let mut m = PartitionMapper::new(Partitioner::Fixed(7), 4);
+        let err = m.partition(&message().partition(3).build()).unwrap_err();
+        assert!(err.is::<FatalError>());
+
+        let mut m = PartitionMapper::new(Partitioner::Fixed(3), 4);
+        assert_eq!(
+            m.partition(&message().partition(0).build()).unwrap(),
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn test_preserve() {
+        let mut m = PartitionMapper::new(Partitioner::Preserve, 4);
+        assert_eq!(
+            m.partition(&message().partition(0).build()).unwrap(),
+            Some(0)
+        );
+        assert_eq!(
+            m.partition(&message().partition(3).build()).unwrap(),
+            Some(3)
+        );
+        let err = m.partition(&message().partition(4).build()).unwrap_err();
+        assert!(err.is::<FatalError>());
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let mut m = PartitionMapper::new(Partitioner::RoundRobin, 3);
+        let got = (0..7)
+            .map(|_| {
+                m.partition(&message().partition(9).build())
+                    .unwrap()
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(got, [0, 1, 2, 0, 1, 2, 0]);
+    }
+
+    #[test]
+    fn test_murmur2_partition() {
+        let mut m = PartitionMapper::new(Partitioner::Murmur2, 10);
+
+        // (-790332482 & 0x7fffffff) % 10
+        assert_eq!(
+            m.partition(&message().partition(0).key(b"foobar".to_vec()).build())
+                .unwrap(),
+            Some(6)
+        );
+        assert_eq!(
+            m.partition(&message().partition(5).key(b"foobar".to_vec()).build())
+                .unwrap(),
+            Some(6)
+        );
+
+        // Messages without keys are distributed round-robin.
+        assert_eq!(
+            m.partition(&message().partition(0).build()).unwrap(),
+            Some(0)
+        );
+        assert_eq!(
+            m.partition(&message().partition(0).build()).unwrap(),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_modulo() {
+        let mut m = PartitionMapper::new(Partitioner::Modulo, 4);
+        assert_eq!(
+            m.partition(&message().partition(0).build()).unwrap(),
+            Some(0)
+        );
+        assert_eq!(
+            m.partition(&message().partition(3).build()).unwrap(),
+            Some(3)
+        );
+        assert_eq!(
+            m.partition(&message().partition(4).build()).unwrap(),
+            Some(0)
+        );
+        assert_eq!(
+            m.partition(&message().partition(11).build()).unwrap(),
+            Some(3)
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use crate::{
+    cli::common::{Payload, RouteCondition},
+    message::Message,
+};
+
+use super::{DeliveryReport, Sink};
+
+/// A [`Sink`] writing each message to the first sink with a matching
+/// [`RouteCondition`], or the default sink if none match.
+pub struct Router {
+    routes: Vec<(RouteCondition, Box<dyn Sink>)>,
+    default: Box<dyn Sink>,
+}
+
+impl Router {
+    pub fn new(routes: Vec<(RouteCondition, Box<dyn Sink>)>, default: Box<dyn Sink>) -> Self {
+        Self { routes, default }
+    }
+}
+
+impl Sink for Router {
+    fn write(&mut self, msg: &Message) -> anyhow::Result<()> {
+        let payload = Payload::new(msg);
+        let sink = self
+            .routes
+            .iter_mut()
+            .find(|(c, _)| c.matches(msg, &payload))
+            .map_or(&mut self.default, |(_, s)| s);
+
+        sink.write(msg)
+    }
+
+    fn flush(&mut self) -> anyhow::Result<()> {
+        // Flush every sink, even if one fails.
+        let mut result = Ok(());
+        for sink in self
+            .routes
+            .iter_mut()
+            .map(|(_, s)| s)
+            .chain(std::iter::once(&mut self.default))
+        {
+            if let Err(e) = sink.flush() {
+                result = Err(e);
+            }
+        }
+        result
+    }
+
+    fn delivery_report(&self) -> Option<DeliveryReport> {
+        self.routes
+            .iter()
+            .map(|(_, s)| s)
+            .chain(std::iter::once(&self.default))
+            .filter_map(|s| s.delivery_report())
+            .reduce(|mut acc, v| {
+                acc.merge(v);
+                acc
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::message::test_util::message;
+
+    use super::*;
+
+    /// A sink recording the offsets of the messages written to it.
+    #[derive(Default, Clone)]
+    struct MockSink(Arc<Mutex<Vec<i64>>>);
+
+    impl Sink for MockSink {
+        fn write(&mut self, msg: &Message) -> anyhow::Result<()> {
+            self.0.lock().unwrap().push(msg.offset());
+            Ok(())
+        }
+
+        fn flush(&mut self) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_route() {
+        let (p1, p2, default) = Default::default();
+        let mut router = Router::new(
+            vec![
+                (
+                    "partition:1".parse().unwrap(),
+                    Box::new(MockSink::clone(&p1)),
+                ),
+                // Partition 1 messages are routed by the first matching rule.
+                ("key:.*".parse().unwrap(), Box::new(MockSink::clone(&p2))),
+            ],
+            Box::new(MockSink::clone(&default)),
+        );
+
+        for (offset, partition, key) in [
+            (0, 1, None),
+            (1, 1, Some(vec![1])),
+            (2, 2, Some(vec![2])),
+            (3, 2, None),
+        ] {
+            router
+                .write(
+                    &message()
+                        .partition(partition)
+                        .offset(offset)
+                        .key(key)
+                        .build(),
+                )
+                .unwrap();
+        }
+        router.flush().unwrap();
+
+        assert_eq!(*p1.0.lock().unwrap(), [0, 1]);
+        assert_eq!(*p2.0.lock().unwrap(), [2]);
+        assert_eq!(*default.0.lock().unwrap(), [3]);
+
+        // None of the sinks report deliveries.
+        assert_eq!(router.delivery_report(), None);
+    }
+}
@@ -0,0 +1,411 @@
+use std::{
+    sync::{
+        mpsc::{sync_channel, Receiver, SyncSender},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use clap::ArgEnum;
+use indicatif::ProgressBar;
+
+use crate::message::Message;
+
+use super::{DeliveryReport, FatalError, Sink};
+
+/// The behaviour of a [`Tee`] when writing to one of its sinks fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ArgEnum)]
+#[clap(rename_all = "kebab-case")]
+pub enum TeePolicy {
+    /// Stop writing to all sinks.
+    FailAll,
+
+    /// Stop writing to the failed sink, and continue writing to the others.
+    Continue,
+}
+
+/// The delay between retries of a failed write or flush.
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// A [`Sink`] writing every message to several sinks.
+///
+/// Each sink is written to from its own thread, with its own buffer, so a
+/// slow sink only blocks the others once its buffer is full.
+///
+/// As with a single sink, failed writes and flushes are retried until they
+/// succeed, unless the error is a [`FatalError`] - only then is the sink
+/// considered failed, and the [`TeePolicy`] applied.
+pub struct Tee {
+    branches: Vec<Branch>,
+    policy: TeePolicy,
+    pb: ProgressBar,
+}
+
+impl Tee {
+    /// Write to each of the named `sinks`, buffering up to `buffer` messages
+    /// for each, and reporting errors through `pb`.
+    pub fn new(
+        sinks: Vec<(String, Box<dyn Sink>)>,
+        buffer: usize,
+        policy: TeePolicy,
+        pb: ProgressBar,
+    ) -> Self {
+        Self {
+            branches: sinks
+                .into_iter()
+                .map(|(name, sink)| Branch::spawn(name, sink, buffer, pb.clone()))
+                .collect(),
+            policy,
+            pb,
+        }
+    }
+
+    /// Report any newly failed branches, returning an error if the copy
+    /// cannot continue.
+    fn check(&mut self) -> anyhow::Result<()> {
+        let mut errors = Vec::new();
+        for b in &mut self.branches {
+            if let Some(e) = &b.state.lock().unwrap().error {
+                if !b.reported {
+                    self.pb
+                        .println(format!("[-] write to {} failed: {}", b.name, e));
+                    b.reported = true;
+                }
+                errors.push(format!("{}: {}", b.name, e));
+            }
+        }
+
+        match self.policy {
+            TeePolicy::FailAll if !errors.is_empty() => Err(FatalError(anyhow::anyhow!(
+                "write to {} of {} destinations failed ({})",
+                errors.len(),
+                self.branches.len(),
+                errors.join(", ")
+            ))
+            .into()),
+            _ if errors.len() == self.branches.len() => Err(FatalError(anyhow::anyhow!(
+                "write to all destinations failed ({})",
+                errors.join(", ")
+            ))
+            .into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Sink for Tee {
+    fn write(&mut self, msg: &Message) -> anyhow::Result<()> {
+        self.check()?;
+
+        for b in &self.branches {
+            b.send(Command::Write(msg.clone()));
+        }
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> anyhow::Result<()> {
+        // Flush all branches concurrently.
+        let pending = self
+            .branches
+            .iter()
+            .map(|b| {
+                let (tx, rx) = sync_channel(1);
+                b.send(Command::Flush(tx));
+                rx
+            })
+            .collect::<Vec<_>>();
+
+        // A branch that has failed drops the flush channel without replying.
+        for rx in pending {
+            let _ = rx.recv();
+        }
+
+        self.check()
+    }
+
+    fn delivery_report(&self) -> Option<DeliveryReport> {
+        let mut out: Option<DeliveryReport> = None;
+
+        for b in &self.branches {
+            let state = b.state.lock().unwrap();
+
+            let mut report = state.report.clone().unwrap_or_default();
+            if let Some(e) = &state.error {
+                if state.skipped > 0 {
+                    report
+                        .failed
+                        .insert(format!("write to {} failed: {}", b.name, e), state.skipped);
+                }
+            } else if state.report.is_none() {
+                continue;
+            }
+
+            match &mut out {
+                Some(v) => v.merge(report),
+                None => out = Some(report),
+            }
+        }
+
+        out
+    }
+}
+
+impl Drop for Tee {
+    fn drop(&mut self) {
+        // Close all the channels before waiting on any thread.
+        for b in &mut self.branches {
+            b.tx.take();
+        }
+        for b in &mut self.branches {
+            if let Some(h) = b.handle.take() {
+                let _ = h.join();
+            }
+        }
+    }
+}
+
+enum Command {
+    Write(Message),
+    Flush(SyncSender<()>),
+}
+
+#[derive(Default)]
+struct BranchState {
+    /// The error that caused this branch to stop writing.
+    error: Option<String>,
+
+    /// The number of messages not written due to `error`.
+    skipped: u64,
+
+    /// The delivery report of the sink as of the last flush.
+    report: Option<DeliveryReport>,
+}
+
+/// A sink written to from a dedicated thread.
+struct Branch {
+    name: String,
+    tx: Option<SyncSender<Command>>,
+    handle: Option<JoinHandle<()>>,
+    state: Arc<Mutex<BranchState>>,
+
+    /// True once the failure of this branch has been reported.
+    reported: bool,
+}
+
+impl Branch {
+    fn spawn(name: String, sink: Box<dyn Sink>, buffer: usize, pb: ProgressBar) -> Self {
+        let (tx, rx) = sync_channel(buffer);
+        let state = Arc::new(Mutex::new(BranchState::default()));
+
+        let handle = std::thread::spawn({
+            let state = Arc::clone(&state);
+            let name = name.clone();
+            move || run_branch(&name, sink, rx, state, pb)
+        });
+
+        Self {
+            name,
+            tx: Some(tx),
+            handle: Some(handle),
+            state,
+            reported: false,
+        }
+    }
+
+    fn send(&self, cmd: Command) {
+        // The branch thread never exits while the channel is open.
+        if let Some(tx) = &self.tx {
+            tx.send(cmd).expect("tee branch thread died");
+        }
+    }
+}
+
+fn run_branch(
+    name: &str,
+    mut sink: Box<dyn Sink>,
+    rx: Receiver<Command>,
+    state: Arc<Mutex<BranchState>>,
+    pb: ProgressBar,
+) {
+    let mut failed = false;
+
+    for cmd in rx {
+        if failed {
+            // Keep draining the channel so the tee never blocks on a failed
+            // branch.
+            if let Command::Write(_) = cmd {
+                state.lock().unwrap().skipped += 1;
+            }
+            continue;
+        }
+
+        let result = match cmd {
+            Command::Write(msg) => retry(
+                || sink.write(&msg),
+                |e| pb.println(format!("[-] write error ({}): {}", name, e)),
+            ),
+            Command::Flush(done) => {
+                let result = retry(
+                    || sink.flush(),
+                    |e| pb.println(format!("[-] write flush error ({}): {}", name, e)),
+                );
+                state.lock().unwrap().report = sink.delivery_report();
+                if result.is_ok() {
+                    let _ = done.send(());
+                }
+                result
+            }
+        };
+
+        if let Err(e) = result {
+            let mut state = state.lock().unwrap();
+            state.error = Some(format!("{:#}", e));
+            state.skipped += 1;
+            failed = true;
+        }
+    }
+}
+
+/// Call `op` until it succeeds or returns a [`FatalError`], passing any
+/// other errors to `report` before retrying.
+fn retry<F, R>(mut op: F, mut report: R) -> anyhow::Result<()>
+where
+    F: FnMut() -> anyhow::Result<()>,
+    R: FnMut(&anyhow::Error),
+{
+    loop {
+        match op() {
+            Ok(_) => return Ok(()),
+            Err(e) if e.is::<FatalError>() => return Err(e),
+            Err(e) => report(&e),
+        }
+        std::thread::sleep(RETRY_DELAY);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::message::test_util::message;
+
+    use super::*;
+
+    /// A sink recording the offsets of the messages written to it, failing
+    /// writes of the offset in `fail_at` with a [`FatalError`], and the first
+    /// write of the offset in `retry_at` with a transient error.
+    #[derive(Default, Clone)]
+    struct MockSink {
+        written: Arc<Mutex<Vec<i64>>>,
+        fail_at: Option<i64>,
+        retry_at: Arc<Mutex<Option<i64>>>,
+    }
+
+    impl Sink for MockSink {
+        fn write(&mut self, msg: &Message) -> anyhow::Result<()> {
+            if Some(msg.offset()) == self.fail_at {
+                return Err(FatalError(anyhow::anyhow!("bananas")).into());
+            }
+            let mut retry_at = self.retry_at.lock().unwrap();
+            if Some(msg.offset()) == *retry_at {
+                *retry_at = None;
+                return Err(anyhow::anyhow!("queue full"));
+            }
+            self.written.lock().unwrap().push(msg.offset());
+            Ok(())
+        }
+
+        fn flush(&mut self) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn new_tee(policy: TeePolicy) -> (Tee, MockSink, MockSink) {
+        let ok = MockSink::default();
+        let failing = MockSink {
+            fail_at: Some(1),
+            ..Default::default()
+        };
+
+        let tee = Tee::new(
+            vec![
+                ("ok".to_string(), Box::new(ok.clone())),
+                ("failing".to_string(), Box::new(failing.clone())),
+            ],
+            1,
+            policy,
+            ProgressBar::hidden(),
+        );
+
+        (tee, ok, failing)
+    }
+
+    #[test]
+    fn test_continue() {
+        let (mut tee, ok, failing) = new_tee(TeePolicy::Continue);
+
+        for i in 0..4 {
+            tee.write(&message().offset(i).build())
+                .expect("continues after branch failure");
+        }
+        tee.flush().expect("continues after branch failure");
+
+        assert_eq!(*ok.written.lock().unwrap(), [0, 1, 2, 3]);
+        assert_eq!(*failing.written.lock().unwrap(), [0]);
+
+        let report = tee.delivery_report().expect("failed branch is reported");
+        assert_eq!(report.failed_count(), 3);
+        assert_eq!(
+            report.failed.keys().collect::<Vec<_>>(),
+            ["write to failing failed: bananas"]
+        );
+    }
+
+    #[test]
+    fn test_fail_all() {
+        let (mut tee, _ok, _failing) = new_tee(TeePolicy::FailAll);
+
+        tee.write(&message().offset(0).build())
+            .expect("first write succeeds");
+        tee.write(&message().offset(1).build())
+            .expect("failure is asynchronous");
+
+        // The failure is surfaced by the flush at the latest.
+        let err = tee.flush().expect_err("branch failure is fatal");
+        assert!(err.is::<FatalError>());
+        assert!(tee.write(&message().offset(2).build()).is_err());
+    }
+
+    #[test]
+    fn test_retry_transient_error() {
+        let flaky = MockSink {
+            retry_at: Arc::new(Mutex::new(Some(1))),
+            ..Default::default()
+        };
+        let mut tee = Tee::new(
+            vec![("flaky".to_string(), Box::new(flaky.clone()))],
+            1,
+            TeePolicy::FailAll,
+            ProgressBar::hidden(),
+        );
+
+        for i in 0..3 {
+            tee.write(&message().offset(i).build())
+                .expect("transient errors are retried");
+        }
+        tee.flush().expect("transient errors are retried");
+
+        assert_eq!(*flaky.written.lock().unwrap(), [0, 1, 2]);
+        assert_eq!(tee.delivery_report(), None);
+    }
+
+    #[test]
+    fn test_no_reports() {
+        let (mut tee, _ok, _failing) = new_tee(TeePolicy::Continue);
+
+        tee.write(&message().offset(0).build()).unwrap();
+        tee.flush().unwrap();
+
+        assert_eq!(tee.delivery_report(), None);
+    }
+}
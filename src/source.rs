@@ -1,5 +1,6 @@
 pub mod file;
 pub mod kafka;
+pub mod ndjson;
 
 use crate::{
     cli::common::{KafkaOpts, Target},
@@ -8,10 +9,32 @@ use crate::{
 
 type BoxedSource = Box<dyn Iterator<Item = Result<Message, Box<dyn std::error::Error>>>>;
 
+/// The position within a partition at which a source begins reading.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StartPosition {
+    /// An absolute offset, or an offset relative to the end of the partition
+    /// if negative.
+    Offset(i64),
+
+    /// The first message with a timestamp (milliseconds since epoch) equal to
+    /// or greater than this value, as resolved by the broker.
+    Timestamp(i64),
+
+    /// The offset committed by the named consumer group, or the beginning of
+    /// the partition if the group has no committed offset.
+    Committed {
+        group: String,
+
+        /// The cluster storing the group's offsets, if not the cluster being
+        /// read from.
+        brokers: Option<Vec<String>>,
+    },
+}
+
 pub(crate) fn init(
     target: Target,
     kafka_opts: &KafkaOpts,
-    start_offset: Option<i64>,
+    start: Option<StartPosition>,
 ) -> anyhow::Result<BoxedSource> {
     match target {
         Target::Kafka {
@@ -21,17 +44,30 @@ pub(crate) fn init(
         } => {
             eprintln!("[*] connecting to kafka brokers: {}", brokers.join(", "));
             Ok(Box::new(kafka::new(
-                brokers,
-                topic,
-                partition,
-                kafka_opts,
-                start_offset,
+                brokers, topic, partition, kafka_opts, start,
+            )?))
+        }
+        Target::KafkaPattern { brokers, pattern } => {
+            eprintln!("[*] connecting to kafka brokers: {}", brokers.join(", "));
+            Ok(Box::new(kafka::new_pattern(
+                brokers, &pattern, kafka_opts, start,
             )?))
         }
         Target::Path(v) => {
             eprintln!("[*] opening dump file: {}", v.display());
             Ok(Box::new(file::new(v)?))
         }
+        Target::Stdio => {
+            eprintln!("[*] reading dump from stdin");
+            Ok(Box::new(file::stdin_stream()))
+        }
+        Target::Ndjson(v) => {
+            match &v {
+                Some(v) => eprintln!("[*] opening ndjson file: {}", v.display()),
+                None => eprintln!("[*] reading ndjson from stdin"),
+            }
+            Ok(Box::new(ndjson::new(v)?))
+        }
     }
 }
 
@@ -1,4 +1,8 @@
-use std::{fs::File, io::BufReader, path::PathBuf};
+use std::{
+    fs::File,
+    io::{stdin, BufReader, Read},
+    path::PathBuf,
+};
 
 use anyhow::Context;
 
@@ -13,13 +17,20 @@ pub(crate) fn new(
     let f = File::open(&path)
         .with_context(|| format!("failed to open file {} for reading", path.display()))?;
 
+    Ok(from_reader(f))
+}
+
+/// Read a dump streamed through stdin.
+pub(crate) fn stdin_stream() -> impl Iterator<Item = Result<Message, Box<dyn std::error::Error>>> {
+    from_reader(stdin())
+}
+
+fn from_reader(r: impl Read) -> impl Iterator<Item = Result<Message, Box<dyn std::error::Error>>> {
     // Use buffered I/O for increased performance.
-    let mut f = BufReader::new(f);
+    let mut f = BufReader::new(r);
 
-    Ok(std::iter::from_fn(
-        move || match file_codec::deserialise_from(&mut f) {
-            Err(CodecError::Eof) => None,
-            v => Some(v.map_err(Into::into)),
-        },
-    ))
+    std::iter::from_fn(move || match file_codec::deserialise_from(&mut f) {
+        Err(CodecError::Eof) => None,
+        v => Some(v.map_err(Into::into)),
+    })
 }
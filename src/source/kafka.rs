@@ -1,25 +1,37 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    ops::RangeInclusive,
+};
+
 use anyhow::Context;
 use rdkafka::{
     config::FromClientConfig,
-    consumer::{BaseConsumer, Consumer},
+    consumer::{BaseConsumer, CommitMode, Consumer},
+    error::KafkaError,
     message::{BorrowedMessage, Headers},
     util::Timeout,
     Message as _, Offset, TopicPartitionList,
 };
 
 use crate::{
-    cli::common::KafkaOpts,
+    cli::common::{KafkaOpts, Target, TopicPattern},
     message::{Message, Timestamp},
+    topic_config,
 };
 
+use serde::Serialize;
+
+use super::StartPosition;
+
 pub fn new(
     brokers: Vec<String>,
     topic: String,
     partition: Option<i32>,
     kafka_opts: &KafkaOpts,
-    start_offset: Option<i64>,
+    start: Option<StartPosition>,
 ) -> anyhow::Result<impl Iterator<Item = Result<Message, Box<dyn std::error::Error>>>> {
-    let config = kafka_opts.new_kafka_config(brokers);
+    let config = kafka_opts.new_consumer_config(brokers.clone());
 
     let consumer =
         BaseConsumer::from_config(&config).context("failed to initialise kafka consumer")?;
@@ -43,46 +55,903 @@ pub fn new(
     // If end != None, use that and skip lookup
     // Make iterator sized, and use to measure progress
 
+    let tp = (topic.clone(), partition);
+    let committed = start_committed(
+        &brokers,
+        std::slice::from_ref(&tp),
+        start.as_ref(),
+        kafka_opts,
+    )?;
+    let (offset, first, high) = seek_position(
+        &consumer,
+        &topic,
+        partition,
+        start.as_ref(),
+        &committed,
+        kafka_opts,
+        true,
+    )?;
+
+    let mut targets = TopicPartitionList::new();
+    targets
+        .add_partition_offset(&topic, partition, offset)
+        .context("failed to configure partition config")?;
+
+    consume(consumer, targets, high - first, kafka_opts)
+}
+
+/// Resolve the offset reading each `(topic, partition)` in `partitions` from
+/// `start` begins at, and the high watermark it is read up to.
+pub fn read_positions(
+    brokers: Vec<String>,
+    partitions: &[(String, i32)],
+    start: Option<&StartPosition>,
+    kafka_opts: &KafkaOpts,
+) -> anyhow::Result<BTreeMap<(String, i32), (i64, i64)>> {
+    let consumer = watermark_consumer(brokers.clone(), kafka_opts)?;
+    let committed = start_committed(&brokers, partitions, start, kafka_opts)?;
+
+    partitions
+        .iter()
+        .map(|(topic, partition)| {
+            let (_, first, high) = seek_position(
+                &consumer, topic, *partition, start, &committed, kafka_opts, false,
+            )?;
+            Ok(((topic.clone(), *partition), (first, high)))
+        })
+        .collect()
+}
+
+/// Return the sorted `(topic, partition)` pairs on `brokers` selected by
+/// `target`.
+pub fn resolve_partitions(
+    brokers: &[String],
+    target: &Target,
+    kafka_opts: &KafkaOpts,
+) -> anyhow::Result<Vec<(String, i32)>> {
+    if let Target::Kafka {
+        topic,
+        partition: Some(p),
+        ..
+    } = target
+    {
+        return Ok(vec![(topic.clone(), *p)]);
+    }
+
+    let consumer = BaseConsumer::from_config(&kafka_opts.new_consumer_config(brokers.to_vec()))
+        .context("failed to initialise kafka consumer")?;
+
+    let meta = consumer
+        .fetch_metadata(None, kafka_opts.timeout)
+        .context("failed to read cluster metadata")?;
+
+    let mut partitions = meta
+        .topics()
+        .iter()
+        .filter(|t| match target {
+            Target::Kafka { topic, .. } => t.name() == topic,
+            Target::KafkaPattern { pattern, .. } => pattern.is_match(t.name()),
+            _ => false,
+        })
+        .flat_map(|t| {
+            t.partitions()
+                .iter()
+                .map(|p| (t.name().to_string(), p.id()))
+        })
+        .collect::<Vec<_>>();
+    partitions.sort();
+
+    if partitions.is_empty() {
+        return Err(anyhow::anyhow!("no partitions found for {}", target));
+    }
+
+    Ok(partitions)
+}
+
+/// Consume from all partitions of every topic with a name matching `pattern`.
+///
+/// Each partition is positioned according to `start` independently, and
+/// messages from all partitions are interleaved in the order they are
+/// received.
+pub fn new_pattern(
+    brokers: Vec<String>,
+    pattern: &TopicPattern,
+    kafka_opts: &KafkaOpts,
+    start: Option<StartPosition>,
+) -> anyhow::Result<impl Iterator<Item = Result<Message, Box<dyn std::error::Error>>>> {
+    let config = kafka_opts.new_consumer_config(brokers.clone());
+
+    let consumer =
+        BaseConsumer::from_config(&config).context("failed to initialise kafka consumer")?;
+
+    let meta = consumer
+        .fetch_metadata(None, kafka_opts.timeout)
+        .context("failed to read cluster metadata")?;
+
+    let mut partitions = Vec::new();
+    for topic in meta.topics().iter().filter(|t| pattern.is_match(t.name())) {
+        eprintln!(
+            "[*] selected topic {} ({} partitions)",
+            topic.name(),
+            topic.partitions().len()
+        );
+
+        partitions.extend(
+            topic
+                .partitions()
+                .iter()
+                .map(|p| (topic.name().to_string(), p.id())),
+        );
+    }
+
+    if partitions.is_empty() {
+        return Err(anyhow::anyhow!("no topics match {}", pattern));
+    }
+
+    let committed = start_committed(&brokers, &partitions, start.as_ref(), kafka_opts)?;
+
+    let mut targets = TopicPartitionList::new();
+    let mut remaining = 0;
+    for (topic, partition) in &partitions {
+        let (offset, first, high) = seek_position(
+            &consumer,
+            topic,
+            *partition,
+            start.as_ref(),
+            &committed,
+            kafka_opts,
+            true,
+        )?;
+
+        targets
+            .add_partition_offset(topic, *partition, offset)
+            .context("failed to configure partition config")?;
+        remaining += high - first;
+    }
+
+    consume(consumer, targets, remaining, kafka_opts)
+}
+
+/// Initialise a consumer for reading the high watermark of partitions.
+///
+/// The broker resolves the end of a partition according to the isolation
+/// level of the request - the last stable offset when reading committed
+/// messages - so this consumer always reads uncommitted messages.
+fn watermark_consumer(
+    brokers: Vec<String>,
+    kafka_opts: &KafkaOpts,
+) -> anyhow::Result<BaseConsumer> {
+    let mut config = kafka_opts.new_kafka_config(brokers);
+    config.set("isolation.level", "read_uncommitted");
+
+    BaseConsumer::from_config(&config).context("failed to initialise kafka consumer")
+}
+
+/// Why a range of offsets in a partition was never returned by a consumer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    /// Messages from an aborted transaction.
+    Aborted,
+
+    /// Messages from a transaction that has not yet been committed or
+    /// aborted.
+    Uncommitted,
+
+    /// Transaction control markers.
+    Marker,
+
+    /// Transaction control markers, or messages removed by compaction - the
+    /// two cannot be told apart in a compacted topic.
+    MarkerOrCompacted,
+}
+
+impl Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SkipReason::Aborted => "aborted transactional messages",
+            SkipReason::Uncommitted => "messages in an open transaction",
+            SkipReason::Marker => "transaction markers",
+            SkipReason::MarkerOrCompacted => "transaction markers or compacted messages",
+        })
+    }
+}
+
+/// Identifies why offsets were never returned by a consumer, by re-reading
+/// them with "read_uncommitted" isolation.
+///
+/// Offsets returned when re-read are messages from aborted transactions, or
+/// from open transactions if at or after the last stable offset. Offsets
+/// still not returned are transaction markers or, in a compacted topic,
+/// possibly messages removed by compaction.
+pub struct SkipInspector<'a> {
+    brokers: Vec<String>,
+    kafka_opts: &'a KafkaOpts,
+
+    /// A "read_uncommitted" consumer reporting the end of each partition.
+    uncommitted: BaseConsumer,
+
+    /// A "read_committed" consumer for resolving the last stable offset.
+    committed: BaseConsumer,
+
+    /// Whether each topic inspected so far is compacted.
+    compacted: BTreeMap<String, bool>,
+}
+
+impl<'a> SkipInspector<'a> {
+    pub fn new(brokers: Vec<String>, kafka_opts: &'a KafkaOpts) -> anyhow::Result<Self> {
+        let mut config = kafka_opts.new_kafka_config(brokers.clone());
+        config.set("isolation.level", "read_uncommitted");
+        config.set("enable.partition.eof", "true");
+        let uncommitted =
+            BaseConsumer::from_config(&config).context("failed to initialise kafka consumer")?;
+
+        let mut config = kafka_opts.new_kafka_config(brokers.clone());
+        config.set("isolation.level", "read_committed");
+        let committed =
+            BaseConsumer::from_config(&config).context("failed to initialise kafka consumer")?;
+
+        Ok(Self {
+            brokers,
+            kafka_opts,
+            uncommitted,
+            committed,
+            compacted: BTreeMap::new(),
+        })
+    }
+
+    /// Identify why each offset in `skipped` was never returned, returning
+    /// the contiguous runs of offsets skipped for the same reason.
+    pub fn inspect(
+        &mut self,
+        topic: &str,
+        partition: i32,
+        skipped: RangeInclusive<i64>,
+    ) -> anyhow::Result<Vec<(RangeInclusive<i64>, SkipReason)>> {
+        let returned = self.reread(topic, partition, &skipped)?;
+
+        // Only messages found when re-reading can be in an open transaction.
+        let stable = if returned.is_empty() {
+            i64::MAX
+        } else {
+            match offset_for_time(&self.committed, topic, partition, -1, self.kafka_opts)? {
+                Offset::Offset(v) => v,
+                _ => i64::MAX,
+            }
+        };
+
+        let compacted = match self.compacted.get(topic) {
+            Some(v) => *v,
+            None => {
+                let v = topic_config::is_compacted(self.brokers.clone(), topic, self.kafka_opts)?;
+                self.compacted.insert(topic.to_string(), v);
+                v
+            }
+        };
+
+        Ok(classify_skipped(skipped, &returned, stable, compacted))
+    }
+
+    /// Read the offsets of the messages in `range` visible to a
+    /// "read_uncommitted" consumer.
+    fn reread(
+        &self,
+        topic: &str,
+        partition: i32,
+        range: &RangeInclusive<i64>,
+    ) -> anyhow::Result<BTreeSet<i64>> {
+        let mut targets = TopicPartitionList::new();
+        targets
+            .add_partition_offset(topic, partition, Offset::Offset(*range.start()))
+            .context("failed to configure partition config")?;
+        self.uncommitted
+            .assign(&targets)
+            .context("failed to assign target partition to consumer")?;
+
+        let mut returned = BTreeSet::new();
+        loop {
+            let msg = match self.uncommitted.poll(self.kafka_opts.timeout) {
+                Some(Ok(v)) => v,
+                Some(Err(KafkaError::PartitionEOF(_))) | None => break,
+                Some(Err(e)) => return Err(e).context("failed to re-read skipped offsets"),
+            };
+
+            if msg.topic() != topic || msg.partition() != partition {
+                continue;
+            }
+            if msg.offset() > *range.end() {
+                break;
+            }
+            returned.insert(msg.offset());
+        }
+
+        Ok(returned)
+    }
+}
+
+/// Classify each offset in `skipped` by whether it was `returned` when
+/// re-read with "read_uncommitted" isolation, relative to the last stable
+/// offset `stable`.
+fn classify_skipped(
+    skipped: RangeInclusive<i64>,
+    returned: &BTreeSet<i64>,
+    stable: i64,
+    compacted: bool,
+) -> Vec<(RangeInclusive<i64>, SkipReason)> {
+    let mut runs: Vec<(RangeInclusive<i64>, SkipReason)> = Vec::new();
+    for offset in skipped {
+        let reason = match (returned.contains(&offset), compacted) {
+            (true, _) if offset >= stable => SkipReason::Uncommitted,
+            (true, _) => SkipReason::Aborted,
+            (false, false) => SkipReason::Marker,
+            (false, true) => SkipReason::MarkerOrCompacted,
+        };
+
+        match runs.last_mut() {
+            Some((range, r)) if *r == reason => *range = *range.start()..=offset,
+            _ => runs.push((offset..=offset, reason)),
+        }
+    }
+    runs
+}
+
+/// Assign `targets` to `consumer`, returning an iterator of the consumed
+/// messages that ends once no message is received within the configured
+/// timeout.
+fn consume(
+    consumer: BaseConsumer,
+    targets: TopicPartitionList,
+    remaining: i64,
+    kafka_opts: &KafkaOpts,
+) -> anyhow::Result<impl Iterator<Item = Result<Message, Box<dyn std::error::Error>>>> {
+    consumer
+        .assign(&targets)
+        .context("failed to assign target partition to consumer")?;
+
+    let timeout = kafka_opts.timeout;
+    let iter = std::iter::from_fn(move || {
+        consumer
+            .poll(Timeout::After(timeout))
+            .map(|v| v.map(|v| Message::from(&v)).map_err(Box::from))
+    });
+
+    Ok(super::ApproxBoundedIter(iter, remaining as usize))
+}
+
+/// Resolve the offset to begin consuming `partition` from for the given
+/// `start` position, returning it alongside the numeric offset it resolves to
+/// and the high watermark of the partition.
+///
+/// When starting from a consumer group, its offset is read from `committed`
+/// (as returned by [`start_committed`]). If `report` is true, the position of
+/// the group is printed.
+fn seek_position(
+    consumer: &BaseConsumer,
+    topic: &str,
+    partition: i32,
+    start: Option<&StartPosition>,
+    committed: &BTreeMap<(String, i32), Option<i64>>,
+    kafka_opts: &KafkaOpts,
+    report: bool,
+) -> anyhow::Result<(Offset, i64, i64)> {
     // Grab the max offset to read.
     let (mut offset_start, offset_end) =
-        consumer.fetch_watermarks(&topic, partition, kafka_opts.timeout)?;
+        consumer.fetch_watermarks(topic, partition, kafka_opts.timeout)?;
 
-    // If a start offset was provided, seek the consumer to it to skip the prior
-    // messages.
-    let offset = match start_offset {
-        Some(v) if v < 0 => {
+    // If a start position was provided, seek the consumer to it to skip the
+    // prior messages.
+    let offset = match start {
+        Some(&StartPosition::Offset(v)) if v < 0 => {
             offset_start = offset_end + v;
             Offset::OffsetTail(-v)
         }
-        Some(v) => {
+        Some(&StartPosition::Offset(v)) => {
             offset_start = v;
             Offset::Offset(v)
         }
+        Some(&StartPosition::Timestamp(ts)) => {
+            // Ask the broker for the earliest offset with a timestamp at or
+            // after ts, avoiding reading (and discarding) all prior messages.
+            match offset_for_time(consumer, topic, partition, ts, kafka_opts)? {
+                Offset::Offset(v) => {
+                    offset_start = v;
+                    Offset::Offset(v)
+                }
+                // No message exists at or after ts - start from the end of
+                // the partition.
+                _ => {
+                    offset_start = offset_end;
+                    Offset::End
+                }
+            }
+        }
+        Some(StartPosition::Committed { group, .. }) => {
+            match committed
+                .get(&(topic.to_string(), partition))
+                .copied()
+                .flatten()
+            {
+                Some(v) => {
+                    offset_start = v;
+                    if report {
+                        eprintln!(
+                            "[*] group {} committed offset {} for {}/{} ({} messages behind)",
+                            group,
+                            v,
+                            topic,
+                            partition,
+                            offset_end - v
+                        );
+                    }
+                    Offset::Offset(v)
+                }
+                // The group has never committed an offset for this partition.
+                None => {
+                    if report {
+                        eprintln!(
+                            "[*] group {} has no committed offset for {}/{} ({} messages behind)",
+                            group,
+                            topic,
+                            partition,
+                            offset_end - offset_start
+                        );
+                    }
+                    Offset::Beginning
+                }
+            }
+        }
         None => Offset::Beginning,
     };
 
+    Ok((offset, offset_start, offset_end))
+}
+
+/// The range of offsets in a partition, and the timestamps of the messages at
+/// either end of it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PartitionOffsets {
+    pub topic: String,
+    pub partition: i32,
+
+    /// The low watermark, the offset of the first message in the partition.
+    pub low: i64,
+
+    /// The high watermark, the offset the next message written to the
+    /// partition is assigned.
+    pub high: i64,
+
+    /// The timestamp (milliseconds since epoch) of the first message, if
+    /// any.
+    pub first_timestamp: Option<i64>,
+
+    /// The timestamp (milliseconds since epoch) of the last message, if any.
+    pub last_timestamp: Option<i64>,
+}
+
+impl PartitionOffsets {
+    /// The number of offsets between the watermarks.
+    ///
+    /// This is an upper bound on the number of messages, as transaction
+    /// markers and compacted messages occupy offsets that are never read.
+    pub fn count(&self) -> i64 {
+        self.high - self.low
+    }
+}
+
+/// Read the watermarks of each `(topic, partition)` in `partitions`, and the
+/// timestamps of the first and last message within them.
+///
+/// A message timestamp is [`None`] if the message cannot be read within the
+/// configured timeout, such as when the last offset in a partition is a
+/// transaction marker.
+pub fn partition_offsets(
+    brokers: Vec<String>,
+    partitions: &[(String, i32)],
+    kafka_opts: &KafkaOpts,
+) -> anyhow::Result<Vec<PartitionOffsets>> {
+    let consumer = BaseConsumer::from_config(&kafka_opts.new_consumer_config(brokers.clone()))
+        .context("failed to initialise kafka consumer")?;
+    let watermark_consumer = watermark_consumer(brokers, kafka_opts)?;
+
+    let mut watermarks = Vec::with_capacity(partitions.len());
+    for (topic, partition) in partitions {
+        let (low, high) = watermark_consumer
+            .fetch_watermarks(topic, *partition, kafka_opts.timeout)
+            .with_context(|| format!("failed to read watermarks for {}/{}", topic, partition))?;
+        watermarks.push(((topic.clone(), *partition), low, high));
+    }
+
+    let non_empty = watermarks.iter().filter(|(_, low, high)| high > low);
+    let mut first = timestamps_at(
+        &consumer,
+        non_empty.clone().map(|(tp, low, _)| (tp.clone(), *low)),
+        kafka_opts,
+    )?;
+    let mut last = timestamps_at(
+        &consumer,
+        non_empty.map(|(tp, _, high)| (tp.clone(), high - 1)),
+        kafka_opts,
+    )?;
+
+    Ok(watermarks
+        .into_iter()
+        .map(|(tp, low, high)| PartitionOffsets {
+            first_timestamp: first.remove(&tp).flatten(),
+            last_timestamp: last.remove(&tp).flatten(),
+            topic: tp.0,
+            partition: tp.1,
+            low,
+            high,
+        })
+        .collect())
+}
+
+/// Resolve the earliest offset containing a message with a timestamp equal to
+/// or greater than `ts` (milliseconds since epoch) for each `(topic,
+/// partition)` in `partitions`.
+///
+/// Partitions without such a message map to [`None`].
+pub fn offsets_for_time(
+    brokers: Vec<String>,
+    partitions: &[(String, i32)],
+    ts: i64,
+    kafka_opts: &KafkaOpts,
+) -> anyhow::Result<BTreeMap<(String, i32), Option<i64>>> {
+    offsets_for_times(
+        brokers,
+        partitions.iter().map(|tp| (tp.clone(), ts)),
+        kafka_opts,
+    )
+}
+
+/// Read the high watermark of each `(topic, partition)` in `partitions`,
+/// including any messages in open transactions.
+///
+/// Unlike [`BaseConsumer::fetch_watermarks`], all partitions are queried at
+/// once rather than with a request per partition.
+pub fn high_watermarks(
+    brokers: Vec<String>,
+    partitions: &[(String, i32)],
+    kafka_opts: &KafkaOpts,
+) -> anyhow::Result<BTreeMap<(String, i32), i64>> {
+    end_offsets(
+        &watermark_consumer(brokers, kafka_opts)?,
+        partitions,
+        kafka_opts,
+    )
+}
+
+/// Read the offset after the last message a consumer with the configured
+/// isolation level can read from each `(topic, partition)` in `partitions`.
+///
+/// When reading committed messages this is the last stable offset, before
+/// any open transaction, rather than the high watermark.
+pub fn readable_end_offsets(
+    brokers: Vec<String>,
+    partitions: &[(String, i32)],
+    kafka_opts: &KafkaOpts,
+) -> anyhow::Result<BTreeMap<(String, i32), i64>> {
+    let consumer = BaseConsumer::from_config(&kafka_opts.new_consumer_config(brokers))
+        .context("failed to initialise kafka consumer")?;
+
+    end_offsets(&consumer, partitions, kafka_opts)
+}
+
+/// Resolve the end of each `(topic, partition)` in `partitions`, according to
+/// the isolation level of `consumer`.
+fn end_offsets(
+    consumer: &BaseConsumer,
+    partitions: &[(String, i32)],
+    kafka_opts: &KafkaOpts,
+) -> anyhow::Result<BTreeMap<(String, i32), i64>> {
+    // A timestamp of -1 resolves to the end of the partition.
+    let resolved = resolve_offsets_for_times(
+        consumer,
+        partitions.iter().map(|tp| (tp.clone(), -1)),
+        kafka_opts,
+    )?;
+
+    resolved
+        .into_iter()
+        .map(|(tp, offset)| {
+            let offset = offset
+                .ok_or_else(|| anyhow::anyhow!("no end offset returned for {}/{}", tp.0, tp.1))?;
+            Ok((tp, offset))
+        })
+        .collect()
+}
+
+/// Resolve the earliest offset containing a message with a timestamp equal to
+/// or greater than the timestamp given for each `(topic, partition)` in
+/// `timestamps`.
+///
+/// Partitions without such a message map to [`None`].
+pub fn offsets_for_times<I>(
+    brokers: Vec<String>,
+    timestamps: I,
+    kafka_opts: &KafkaOpts,
+) -> anyhow::Result<BTreeMap<(String, i32), Option<i64>>>
+where
+    I: IntoIterator<Item = ((String, i32), i64)>,
+{
+    let consumer = BaseConsumer::from_config(&kafka_opts.new_consumer_config(brokers))
+        .context("failed to initialise kafka consumer")?;
+
+    resolve_offsets_for_times(&consumer, timestamps, kafka_opts)
+}
+
+/// As [`offsets_for_times`], using `consumer` for the lookup.
+fn resolve_offsets_for_times<I>(
+    consumer: &BaseConsumer,
+    timestamps: I,
+    kafka_opts: &KafkaOpts,
+) -> anyhow::Result<BTreeMap<(String, i32), Option<i64>>>
+where
+    I: IntoIterator<Item = ((String, i32), i64)>,
+{
+    let mut query = TopicPartitionList::new();
+    for ((topic, partition), ts) in timestamps {
+        query
+            .add_partition_offset(&topic, partition, Offset::Offset(ts))
+            .context("failed to configure timestamp query")?;
+    }
+
+    if query.count() == 0 {
+        return Ok(BTreeMap::new());
+    }
+
+    let resolved = consumer
+        .offsets_for_times(query, kafka_opts.timeout)
+        .context("failed to resolve timestamp to offsets")?;
+
+    resolved
+        .elements()
+        .iter()
+        .map(|elem| {
+            elem.error()
+                .context("failed to resolve timestamp to offset")?;
+            let offset = match elem.offset() {
+                Offset::Offset(v) => Some(v),
+                _ => None,
+            };
+            Ok(((elem.topic().to_string(), elem.partition()), offset))
+        })
+        .collect()
+}
+
+/// Read the timestamp of the message at (or the first message after) each
+/// offset in `positions`.
+///
+/// Every position should be below the high watermark of its partition, as
+/// partitions without a message read before the configured timeout elapses
+/// are omitted. Messages without a timestamp map to [`None`].
+pub fn message_timestamps(
+    brokers: Vec<String>,
+    positions: &[((String, i32), i64)],
+    kafka_opts: &KafkaOpts,
+) -> anyhow::Result<BTreeMap<(String, i32), Option<i64>>> {
+    let consumer = BaseConsumer::from_config(&kafka_opts.new_consumer_config(brokers))
+        .context("failed to initialise kafka consumer")?;
+
+    timestamps_at(&consumer, positions.iter().cloned(), kafka_opts)
+}
+
+/// Read the timestamp of the first message at or after each offset in
+/// `positions`, concurrently across all partitions.
+///
+/// Partitions without a message returned before the configured timeout
+/// elapses are omitted, and messages without a timestamp map to [`None`].
+fn timestamps_at<I>(
+    consumer: &BaseConsumer,
+    positions: I,
+    kafka_opts: &KafkaOpts,
+) -> anyhow::Result<BTreeMap<(String, i32), Option<i64>>>
+where
+    I: IntoIterator<Item = ((String, i32), i64)>,
+{
     let mut targets = TopicPartitionList::new();
-    targets
-        .add_partition_offset(&topic, partition, offset)
-        .context("failed to configure partition config")?;
+    for ((topic, partition), offset) in positions {
+        targets
+            .add_partition_offset(&topic, partition, Offset::Offset(offset))
+            .context("failed to configure partition config")?;
+    }
+
+    let mut out = BTreeMap::new();
+    if targets.count() == 0 {
+        return Ok(out);
+    }
+
     consumer
         .assign(&targets)
-        .context("failed to assign target partition to consumer")?;
+        .context("failed to assign target partitions to consumer")?;
 
-    let timeout = kafka_opts.timeout;
-    let iter = std::iter::from_fn(move || {
+    while out.len() < targets.count() {
+        let msg = match consumer.poll(kafka_opts.timeout) {
+            Some(v) => v.context("failed to read message")?,
+            None => break,
+        };
+
+        let tp = (msg.topic().to_string(), msg.partition());
+        if out.contains_key(&tp) {
+            continue;
+        }
+        out.insert(
+            tp,
+            Timestamp::try_from(msg.timestamp())
+                .ok()
+                .map(|v| v.millis()),
+        );
+
+        // Only the first message is needed, so stop fetching the rest of the
+        // partition while waiting on the others.
+        let mut done = TopicPartitionList::new();
+        done.add_partition(msg.topic(), msg.partition());
         consumer
-            .poll(Timeout::After(timeout))
-            .map(|v| v.map(Message::from).map_err(Box::from))
-    });
+            .pause(&done)
+            .context("failed to pause partition consumption")?;
+    }
 
-    let iter = super::ApproxBoundedIter(iter, (offset_end - offset_start) as usize);
+    // Pausing outlives the assignment, so resume the partitions for any later
+    // use of the consumer.
+    consumer
+        .resume(&targets)
+        .context("failed to resume partition consumption")?;
 
-    Ok(Box::new(iter))
+    Ok(out)
 }
 
-impl<'a> From<BorrowedMessage<'a>> for Message {
-    fn from(v: BorrowedMessage<'a>) -> Self {
+/// Resolve the earliest offset in `partition` containing a message with a
+/// timestamp equal to or greater than `ts` (milliseconds since epoch).
+fn offset_for_time(
+    consumer: &BaseConsumer,
+    topic: &str,
+    partition: i32,
+    ts: i64,
+    kafka_opts: &KafkaOpts,
+) -> anyhow::Result<Offset> {
+    let mut query = TopicPartitionList::new();
+    query
+        .add_partition_offset(topic, partition, Offset::Offset(ts))
+        .context("failed to configure timestamp query")?;
+
+    let resolved = consumer
+        .offsets_for_times(query, kafka_opts.timeout)
+        .context("failed to resolve timestamp to offset")?;
+
+    let elem = resolved
+        .find_partition(topic, partition)
+        .ok_or_else(|| anyhow::anyhow!("no offset returned for partition {}", partition))?;
+    elem.error()
+        .context("failed to resolve timestamp to offset")?;
+
+    Ok(elem.offset())
+}
+
+/// Read the offsets committed for each of `partitions` by the consumer group
+/// `start` resumes from, in a single request.
+///
+/// Returns an empty map if `start` is not a [`StartPosition::Committed`].
+fn start_committed(
+    brokers: &[String],
+    partitions: &[(String, i32)],
+    start: Option<&StartPosition>,
+    kafka_opts: &KafkaOpts,
+) -> anyhow::Result<BTreeMap<(String, i32), Option<i64>>> {
+    match start {
+        Some(StartPosition::Committed {
+            group,
+            brokers: group_brokers,
+        }) => committed_offsets(
+            group_brokers.as_deref().unwrap_or(brokers),
+            group,
+            partitions,
+            kafka_opts,
+        ),
+        _ => Ok(BTreeMap::new()),
+    }
+}
+
+/// Read the offsets committed by `group` for each `(topic, partition)` in
+/// `partitions`, mapping partitions without a committed offset to [`None`].
+///
+/// A dedicated consumer configured with the group ID is used for the lookup,
+/// which does not join the group or otherwise modify its state.
+pub fn committed_offsets(
+    brokers: &[String],
+    group: &str,
+    partitions: &[(String, i32)],
+    kafka_opts: &KafkaOpts,
+) -> anyhow::Result<BTreeMap<(String, i32), Option<i64>>> {
+    let mut config = kafka_opts.new_consumer_config(brokers.to_vec());
+    config.set("group.id", group);
+
+    let consumer = BaseConsumer::from_config(&config)
+        .context("failed to initialise kafka group offset consumer")?;
+
+    let mut query = TopicPartitionList::new();
+    for (topic, partition) in partitions {
+        query.add_partition(topic, *partition);
+    }
+
+    let committed = consumer
+        .committed_offsets(query, kafka_opts.timeout)
+        .with_context(|| format!("failed to read committed offsets for group {}", group))?;
+
+    let mut out = BTreeMap::new();
+    for elem in committed.elements() {
+        elem.error()
+            .with_context(|| format!("failed to read committed offsets for group {}", group))?;
+
+        let offset = match elem.offset() {
+            Offset::Offset(v) => Some(v),
+            _ => None,
+        };
+        out.insert((elem.topic().to_string(), elem.partition()), offset);
+    }
+
+    if let Some((_, partition)) = partitions.iter().find(|tp| !out.contains_key(*tp)) {
+        return Err(anyhow::anyhow!(
+            "no offset returned for partition {}",
+            partition
+        ));
+    }
+
+    Ok(out)
+}
+
+/// Commit `offsets` as the next offset to consume for each `(topic,
+/// partition)` pair, on behalf of the consumer group in `kafka_opts`.
+///
+/// This uses a standalone consumer that never joins the group, so commits
+/// succeed only when the group has no active members.
+pub fn commit_offsets<I>(
+    brokers: Vec<String>,
+    kafka_opts: &KafkaOpts,
+    offsets: I,
+) -> anyhow::Result<()>
+where
+    I: IntoIterator<Item = ((String, i32), i64)>,
+{
+    commit_group_offsets(brokers, &kafka_opts.group, kafka_opts, offsets)
+}
+
+/// Commit `offsets` on behalf of `group`, in a single offset commit request.
+///
+/// As with [`commit_offsets`], the commit is rejected by the broker if the
+/// group has active members.
+pub fn commit_group_offsets<I>(
+    brokers: Vec<String>,
+    group: &str,
+    kafka_opts: &KafkaOpts,
+    offsets: I,
+) -> anyhow::Result<()>
+where
+    I: IntoIterator<Item = ((String, i32), i64)>,
+{
+    let mut targets = TopicPartitionList::new();
+    for ((topic, partition), offset) in offsets {
+        targets
+            .add_partition_offset(&topic, partition, Offset::Offset(offset))
+            .context("failed to configure partition config")?;
+    }
+
+    // Nothing was consumed, so there is nothing to commit.
+    if targets.count() == 0 {
+        return Ok(());
+    }
+
+    let mut config = kafka_opts.new_consumer_config(brokers);
+    config.set("group.id", group);
+
+    let consumer =
+        BaseConsumer::from_config(&config).context("failed to initialise kafka consumer")?;
+
+    consumer
+        .commit(&targets, CommitMode::Sync)
+        .with_context(|| format!("failed to commit offsets for group {}", group))
+}
+
+impl<'a> From<&BorrowedMessage<'a>> for Message {
+    fn from(v: &BorrowedMessage<'a>) -> Self {
         // Read the headers into a vec.
         let parsed_headers = v.headers().map(|headers| {
             (0..headers.count())
@@ -104,3 +973,29 @@ impl<'a> From<BorrowedMessage<'a>> for Message {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_skipped() {
+        // An aborted transaction of 3 messages and its marker, followed by a
+        // commit marker and an open transaction.
+        let returned = BTreeSet::from([10, 11, 12, 15, 16]);
+
+        assert_eq!(
+            classify_skipped(10..=16, &returned, 15, false),
+            [
+                (10..=12, SkipReason::Aborted),
+                (13..=14, SkipReason::Marker),
+                (15..=16, SkipReason::Uncommitted),
+            ]
+        );
+
+        assert_eq!(
+            classify_skipped(13..=14, &returned, 15, true),
+            [(13..=14, SkipReason::MarkerOrCompacted)]
+        );
+    }
+}
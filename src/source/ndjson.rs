@@ -0,0 +1,35 @@
+use std::{
+    fs::File,
+    io::{stdin, BufRead, BufReader},
+    path::PathBuf,
+};
+
+use anyhow::{anyhow, Context};
+
+use crate::{json_output::JsonMessage, message::Message};
+
+/// Read newline delimited [`JsonMessage`] objects from the file at `path`, or
+/// stdin if [`None`].
+pub(crate) fn new(
+    path: Option<PathBuf>,
+) -> anyhow::Result<impl Iterator<Item = Result<Message, Box<dyn std::error::Error>>>> {
+    let r: Box<dyn BufRead> = match path {
+        Some(path) => {
+            let f = File::open(&path)
+                .with_context(|| format!("failed to open file {} for reading", path.display()))?;
+            Box::new(BufReader::new(f))
+        }
+        None => Box::new(BufReader::new(stdin())),
+    };
+
+    Ok(r.lines()
+        .enumerate()
+        // Tolerate blank lines, such as a trailing newline added by an editor.
+        .filter(|(_, line)| !matches!(line, Ok(v) if v.trim().is_empty()))
+        .map(|(n, line)| {
+            let line = line?;
+            let msg = serde_json::from_str::<JsonMessage>(&line)
+                .map_err(|e| anyhow!("invalid message on line {}: {}", n + 1, e))?;
+            Ok(Message::from(msg))
+        }))
+}
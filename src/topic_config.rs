@@ -0,0 +1,235 @@
+//! Topic-level configuration, used to create a destination topic matching the
+//! layout and settings of a source topic.
+
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use rdkafka::{
+    admin::{
+        AdminClient, AdminOptions, ConfigSource, NewTopic, ResourceSpecifier, TopicReplication,
+    },
+    client::DefaultClientContext,
+    config::FromClientConfig,
+    types::RDKafkaErrorCode,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::cli::common::KafkaOpts;
+
+/// The file name suffix of the topic configuration saved alongside a dump
+/// file.
+const SIDECAR_SUFFIX: &str = ".topic.json";
+
+/// The partition count, replication factor and topic-level configuration
+/// overrides of a topic.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TopicSpec {
+    pub partitions: i32,
+    pub replication_factor: i32,
+
+    /// Configuration explicitly set on the topic (such as "cleanup.policy" or
+    /// "retention.ms"), excluding values inherited from the broker defaults.
+    pub configs: BTreeMap<String, String>,
+}
+
+impl TopicSpec {
+    /// Read the specification of `topic` from the cluster.
+    pub fn describe(
+        brokers: Vec<String>,
+        topic: &str,
+        kafka_opts: &KafkaOpts,
+    ) -> anyhow::Result<Self> {
+        let admin = new_admin(brokers, kafka_opts)?;
+
+        let meta = admin
+            .inner()
+            .fetch_metadata(Some(topic), kafka_opts.timeout)
+            .context("failed to read source topic metadata")?;
+
+        let (partitions, replication_factor) = meta
+            .topics()
+            .iter()
+            .find(|t| t.name() == topic && !t.partitions().is_empty())
+            .map(|t| {
+                (
+                    t.partitions().len() as i32,
+                    t.partitions()[0].replicas().len() as i32,
+                )
+            })
+            .ok_or_else(|| anyhow::anyhow!("source topic {} does not exist", topic))?;
+
+        let opts = AdminOptions::new().request_timeout(Some(kafka_opts.timeout));
+        let resource = ResourceSpecifier::Topic(topic);
+        let described = futures::executor::block_on(admin.describe_configs([&resource], &opts))
+            .context("failed to describe source topic configuration")?;
+
+        let mut configs = BTreeMap::new();
+        for result in described {
+            let resource = result.map_err(|e| {
+                anyhow::anyhow!("failed to describe source topic configuration: {}", e)
+            })?;
+
+            configs.extend(
+                resource
+                    .entries
+                    .into_iter()
+                    .filter(|e| e.source == ConfigSource::DynamicTopic && !e.is_read_only)
+                    .filter_map(|e| Some((e.name, e.value?))),
+            );
+        }
+
+        Ok(Self {
+            partitions,
+            replication_factor,
+            configs,
+        })
+    }
+
+    /// The path of the topic configuration saved alongside the dump file at
+    /// `path`.
+    pub fn sidecar_path(path: &Path) -> PathBuf {
+        let mut name = OsString::from(path.as_os_str());
+        name.push(SIDECAR_SUFFIX);
+        PathBuf::from(name)
+    }
+
+    /// Read the topic configuration saved alongside the dump file at `path`,
+    /// if any.
+    pub fn load(path: &Path) -> anyhow::Result<Option<Self>> {
+        let path = Self::sidecar_path(path);
+        let f = match File::open(&path) {
+            Ok(v) => v,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("failed to open {}", path.display())),
+        };
+
+        serde_json::from_reader(BufReader::new(f))
+            .with_context(|| format!("failed to parse topic configuration {}", path.display()))
+            .map(Some)
+    }
+
+    /// Save this topic configuration alongside the dump file at `path`.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let path = Self::sidecar_path(path);
+        let f = File::create(&path)
+            .with_context(|| format!("failed to open {} for writing", path.display()))?;
+
+        serde_json::to_writer_pretty(f, self)
+            .with_context(|| format!("failed to write topic configuration {}", path.display()))
+    }
+
+    /// Create `topic` with this specification, returning false if it already
+    /// exists.
+    pub fn create(
+        &self,
+        brokers: Vec<String>,
+        topic: &str,
+        kafka_opts: &KafkaOpts,
+    ) -> anyhow::Result<bool> {
+        let admin = new_admin(brokers, kafka_opts)?;
+
+        let new_topic = self.configs.iter().fold(
+            NewTopic::new(
+                topic,
+                self.partitions,
+                TopicReplication::Fixed(self.replication_factor),
+            ),
+            |acc, (k, v)| acc.set(k, v),
+        );
+
+        let opts = AdminOptions::new()
+            .request_timeout(Some(kafka_opts.timeout))
+            .operation_timeout(Some(kafka_opts.timeout));
+        let results = futures::executor::block_on(admin.create_topics([&new_topic], &opts))
+            .context("failed to create destination topic")?;
+
+        for result in results {
+            match result {
+                Ok(_) => {}
+                Err((_, RDKafkaErrorCode::TopicAlreadyExists)) => return Ok(false),
+                Err((name, e)) => {
+                    return Err(anyhow::anyhow!("failed to create topic {}: {}", name, e))
+                }
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+/// Returns true if `topic` is compacted, either by its own configuration or
+/// the broker default.
+pub fn is_compacted(
+    brokers: Vec<String>,
+    topic: &str,
+    kafka_opts: &KafkaOpts,
+) -> anyhow::Result<bool> {
+    let admin = new_admin(brokers, kafka_opts)?;
+
+    let opts = AdminOptions::new().request_timeout(Some(kafka_opts.timeout));
+    let resource = ResourceSpecifier::Topic(topic);
+    let described = futures::executor::block_on(admin.describe_configs([&resource], &opts))
+        .context("failed to describe topic configuration")?;
+
+    for result in described {
+        let resource =
+            result.map_err(|e| anyhow::anyhow!("failed to describe topic configuration: {}", e))?;
+
+        if let Some(v) = resource
+            .get("cleanup.policy")
+            .and_then(|e| e.value.as_deref())
+        {
+            return Ok(v.split(',').any(|p| p.trim() == "compact"));
+        }
+    }
+
+    Ok(false)
+}
+
+fn new_admin(
+    brokers: Vec<String>,
+    kafka_opts: &KafkaOpts,
+) -> anyhow::Result<AdminClient<DefaultClientContext>> {
+    AdminClient::from_config(&kafka_opts.new_kafka_config(brokers))
+        .context("failed to initialise kafka admin client")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sidecar_path() {
+        assert_eq!(
+            TopicSpec::sidecar_path(Path::new("./backup/orders.kbin")),
+            PathBuf::from("./backup/orders.kbin.topic.json")
+        );
+    }
+
+    #[test]
+    fn test_save_load() {
+        let path = std::env::temp_dir().join(format!("ktool-spec-{}.kbin", std::process::id()));
+
+        assert_eq!(TopicSpec::load(&path).unwrap(), None);
+
+        let spec = TopicSpec {
+            partitions: 12,
+            replication_factor: 3,
+            configs: [("cleanup.policy".to_string(), "compact".to_string())]
+                .into_iter()
+                .collect(),
+        };
+        spec.save(&path).unwrap();
+
+        let got = TopicSpec::load(&path).unwrap();
+        std::fs::remove_file(TopicSpec::sidecar_path(&path)).unwrap();
+
+        assert_eq!(got, Some(spec));
+    }
+}
@@ -30,7 +30,7 @@ fn test_cp() {
 
     assert_output_contains!(output.stderr, "read complete");
     assert_output_contains!(output.stderr, "write complete");
-    assert_output_contains!(output.stdout, "complete - copied 1 messages");
+    assert_output_contains!(output.stderr, "complete - copied 1 messages");
     assert!(output.status.success());
 
     let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
@@ -66,3 +66,454 @@ fn test_read_file_json() {
     assert_output_contains!(output.stdout, READ_JSON);
     assert!(output.status.success());
 }
+
+#[test]
+fn test_cp_commit() {
+    let addr = maybe_skip_integration!();
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("cp")
+        .arg("./tests/fixture.kbin")
+        .arg(format!("kafka://{}/commit-topic", addr));
+    cmd.unwrap();
+
+    // The first copy reads everything, and the second only what has been
+    // produced since.
+    for want in ["copied 1 messages", "copied 0 messages"] {
+        let path = std::env::temp_dir().join(format!(
+            "ktool-commit-{}-{}.kbin",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+
+        let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+        cmd.arg("cp")
+            .arg(format!("kafka://{}/commit-topic", addr))
+            .arg(&path)
+            .arg("--commit")
+            .arg("--group")
+            .arg("incremental-backup");
+
+        let output = cmd.unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_output_contains!(output.stderr, "committed offsets");
+        assert_output_contains!(output.stdout, want);
+        assert!(output.status.success());
+    }
+}
+
+#[test]
+fn test_cp_dir() {
+    let dir = std::env::temp_dir().join(format!("ktool-dir-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("cp").arg("./tests/fixture.kbin").arg(&dir);
+
+    let output = cmd.unwrap();
+
+    assert_output_contains!(output.stderr, "one file per topic");
+    assert_output_contains!(output.stderr, "complete - copied 1 messages");
+    assert!(output.status.success());
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("read").arg(dir.join("topic.kbin"));
+
+    let output = cmd.unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_output_contains!(output.stdout, READ_HUMAN);
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cp_delivery_failure() {
+    let path = std::env::temp_dir().join(format!("ktool-failed-{}.kbin", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    // Nothing listens on port 1, so delivery times out.
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("cp")
+        .arg("./tests/fixture.kbin")
+        .arg("kafka://127.0.0.1:1/topic")
+        .arg("-X")
+        .arg("message.timeout.ms=500")
+        .arg("--failed-messages")
+        .arg(&path);
+
+    let output = cmd.output().unwrap();
+
+    assert_output_contains!(output.stderr, "1 messages failed delivery");
+    assert_output_contains!(output.stderr, "1 of 1 messages failed delivery");
+    assert!(!output.status.success());
+
+    // The undelivered message can be read back, with its original location.
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("read").arg(&path);
+
+    let output = cmd.unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_output_contains!(output.stdout, READ_HUMAN);
+}
+
+#[test]
+fn test_cp_exactly_once_requires_kafka_source() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("cp")
+        .arg("./tests/fixture.kbin")
+        .arg("kafka://127.0.0.1:1/topic")
+        .arg("--exactly-once");
+
+    let output = cmd.output().unwrap();
+
+    assert_output_contains!(output.stderr, "requires a kafka source and a kafka sink");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_cp_create_topic_requires_config() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("cp")
+        .arg("./tests/fixture.kbin")
+        .arg("kafka://127.0.0.1:1/topic")
+        .arg("--create-topic");
+
+    let output = cmd.output().unwrap();
+
+    assert_output_contains!(output.stderr, "specify --partitions to create the topic");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_cp_create_topic_route_target() {
+    let path = std::env::temp_dir().join(format!("ktool-create-route-{}.kbin", std::process::id()));
+
+    // The routed kafka sink is created, rather than left to auto-creation.
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("cp")
+        .arg("./tests/fixture.kbin")
+        .arg(&path)
+        .arg("--route")
+        .arg("partition:42 => kafka://127.0.0.1:1/topic")
+        .arg("--create-topic");
+
+    let output = cmd.output().unwrap();
+    let _ = std::fs::remove_file(&path);
+
+    assert_output_contains!(output.stderr, "specify --partitions to create the topic");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_cp_amplify() {
+    let path = std::env::temp_dir().join(format!("ktool-amplify-{}.kbin", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("cp")
+        .arg("./tests/fixture.kbin")
+        .arg(&path)
+        .arg("--repeat")
+        .arg("2")
+        .arg("--streams")
+        .arg("3")
+        .arg("--key-template")
+        .arg("{key}-{copy}")
+        .arg("--stamp-header")
+        .arg("copy");
+
+    let output = cmd.unwrap();
+
+    assert_output_contains!(output.stderr, "starting pass 2 of 2");
+    assert_output_contains!(output.stderr, "complete - copied 6 messages");
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("read").arg(&path);
+
+    let output = cmd.unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    for copy in 0..6 {
+        assert_output_contains!(
+            output.stdout,
+            &format!(
+                r#"headers: "copy => {copy}", key: Some("banana-key-{copy}"), payload: Some("platanos")"#
+            )
+        );
+    }
+}
+
+#[test]
+fn test_cp_route() {
+    let dir = std::env::temp_dir().join(format!("ktool-route-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("cp")
+        .arg("./tests/fixture.kbin")
+        .arg(dir.join("default.kbin"))
+        .arg("--route")
+        .arg(format!(
+            "partition:42 => {}",
+            dir.join("p42.kbin").display()
+        ))
+        .arg("--route")
+        .arg(format!(
+            "key:^banana => {}",
+            dir.join("bananas.kbin").display()
+        ));
+
+    let output = cmd.unwrap();
+    assert!(output.status.success());
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("read").arg(dir.join("bananas.kbin"));
+    let bananas = cmd.unwrap();
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("read").arg(dir.join("default.kbin"));
+    let default = cmd.unwrap();
+
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_output_contains!(bananas.stdout, READ_HUMAN);
+    assert!(default.stdout.is_empty());
+}
+
+#[test]
+fn test_cp_tee() {
+    let dir = std::env::temp_dir().join(format!("ktool-tee-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("cp")
+        .arg("./tests/fixture.kbin")
+        .arg(dir.join("a.kbin"))
+        .arg(dir.join("b.kbin"));
+
+    let output = cmd.unwrap();
+    assert!(output.status.success());
+
+    let mut outputs = Vec::new();
+    for name in ["a.kbin", "b.kbin"] {
+        let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+        cmd.arg("read").arg(dir.join(name));
+        outputs.push(cmd.unwrap());
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    for output in outputs {
+        assert_output_contains!(output.stdout, READ_HUMAN);
+    }
+}
+
+#[test]
+fn test_read_show_gaps_requires_kafka() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("read")
+        .arg("./tests/fixture.kbin")
+        .arg("--show-gaps");
+
+    let output = cmd.output().unwrap();
+
+    assert_output_contains!(output.stderr, "--show-gaps requires a kafka source");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_write_requires_kafka() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("write").arg("./bananas.kbin").arg("platanos");
+
+    let output = cmd.output().unwrap();
+
+    assert_output_contains!(output.stderr, "write requires a kafka destination");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_cp_ndjson_round_trip() {
+    let dir = std::env::temp_dir().join(format!("ktool-ndjson-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    // Export the fixture as NDJSON.
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("cp")
+        .arg("./tests/fixture.kbin")
+        .arg(format!("ndjson:{}", dir.join("export.jsonl").display()));
+    cmd.unwrap();
+
+    // And convert it back into a dump file.
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("cp")
+        .arg(format!("ndjson:{}", dir.join("export.jsonl").display()))
+        .arg(dir.join("restored.kbin"));
+    cmd.unwrap();
+
+    let exported = std::fs::read_to_string(dir.join("export.jsonl")).unwrap();
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("read").arg(dir.join("restored.kbin"));
+    let restored = cmd.unwrap();
+
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(exported.trim_end(), READ_JSON);
+    assert_output_contains!(restored.stdout, READ_HUMAN);
+}
+
+#[test]
+fn test_read_ndjson_stdin() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("read")
+        .arg("ndjson:-")
+        .write_stdin(format!("{}\n\n", READ_JSON));
+
+    let output = cmd.unwrap();
+
+    assert_output_contains!(output.stdout, READ_HUMAN);
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_cp_ndjson_stdout() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("cp").arg("./tests/fixture.kbin").arg("ndjson:-");
+
+    let output = cmd.unwrap();
+    assert_output_contains!(output.stderr, "complete - copied 1 messages");
+
+    // Only the messages are written to stdout.
+    assert_eq!(output.stdout, format!("{}\n", READ_JSON).into_bytes());
+}
+
+#[test]
+fn test_cp_stdio() {
+    // Stream the fixture to stdout.
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("cp").arg("./tests/fixture.kbin").arg("-");
+
+    let dump = cmd.unwrap();
+    assert_output_contains!(dump.stderr, "complete - copied 1 messages");
+
+    // The dump is unmodified by any status output.
+    assert_eq!(dump.stdout, std::fs::read("./tests/fixture.kbin").unwrap());
+
+    // And read it back through stdin.
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("read").arg("-").write_stdin(dump.stdout);
+
+    let output = cmd.unwrap();
+    assert_output_contains!(output.stdout, READ_HUMAN);
+}
+
+#[test]
+fn test_cp_stdin_repeat() {
+    let path = std::env::temp_dir().join(format!("ktool-stdin-repeat-{}.kbin", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("cp")
+        .arg("-")
+        .arg(&path)
+        .arg("--repeat")
+        .arg("3")
+        .write_stdin(std::fs::read("./tests/fixture.kbin").unwrap());
+
+    let output = cmd.output().unwrap();
+
+    assert_output_contains!(
+        output.stderr,
+        "--repeat cannot be used when reading from stdin"
+    );
+    assert!(!output.status.success());
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_cp_multiple_stdout_sinks() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("cp")
+        .arg("./tests/fixture.kbin")
+        .arg("-")
+        .arg("ndjson:-");
+
+    let output = cmd.output().unwrap();
+
+    assert_output_contains!(output.stderr, "only one sink can write to stdout");
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn test_offsets_requires_kafka() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("offsets").arg("./tests/fixture.kbin");
+
+    let output = cmd.output().unwrap();
+
+    assert_output_contains!(output.stderr, "offsets requires a kafka topic");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_groups_describe_requires_group() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("groups").arg("describe").arg("127.0.0.1:9092");
+
+    let output = cmd.output().unwrap();
+
+    assert_output_contains!(output.stderr, "<GROUP>");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_groups_reset_requires_strategy() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("groups")
+        .arg("reset")
+        .arg("127.0.0.1:9092")
+        .arg("bananas");
+
+    let output = cmd.output().unwrap();
+
+    assert_output_contains!(output.stderr, "--to-timestamp");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_groups_import_unknown_group() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("groups")
+        .arg("import")
+        .arg("127.0.0.1:1")
+        .arg("-")
+        .arg("--only")
+        .arg("platanos")
+        .write_stdin(r#"{"groups":[{"group":"bananas","offsets":[]}]}"#);
+
+    let output = cmd.output().unwrap();
+
+    assert_output_contains!(output.stderr, "consumer group platanos not found in file");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_groups_import_invalid_file() {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("groups")
+        .arg("import")
+        .arg("127.0.0.1:1")
+        .arg("./tests/fixture.kbin");
+
+    let output = cmd.output().unwrap();
+
+    assert_output_contains!(output.stderr, "failed to parse consumer group offsets");
+    assert!(!output.status.success());
+}
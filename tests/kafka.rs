@@ -1,6 +1,14 @@
 use std::time::Duration;
 
-use ktool::{cli::common::KafkaOpts, message::Message, sink::Sink};
+use assert_cmd::Command;
+
+use ktool::{
+    cli::common::{IsolationLevel, KafkaOpts, ProduceOpts},
+    consumer_group::ResetStrategy,
+    message::{Message, Timestamp},
+    sink::Sink,
+    source::{kafka::SkipReason, StartPosition},
+};
 
 mod common;
 
@@ -13,6 +21,7 @@ fn test_produce_consume() {
     let kafka_config = KafkaOpts {
         timeout: Duration::from_secs(5),
         group: "bananas".to_string(),
+        isolation: IsolationLevel::ReadCommitted,
         additional_args: vec![],
     };
 
@@ -21,6 +30,7 @@ fn test_produce_consume() {
         TOPIC.to_string(),
         Some(0),
         &kafka_config,
+        &ProduceOpts::default(),
     )
     .expect("failed to initialise kafka sink");
 
@@ -61,6 +71,7 @@ fn test_consume_tail() {
     let kafka_config = KafkaOpts {
         timeout: Duration::from_secs(5),
         group: "bananas".to_string(),
+        isolation: IsolationLevel::ReadCommitted,
         additional_args: vec![],
     };
 
@@ -80,6 +91,7 @@ fn test_consume_tail() {
             TOPIC.to_string(),
             Some(0),
             &kafka_config,
+            &ProduceOpts::default(),
         )
         .expect("failed to initialise kafka sink");
 
@@ -106,7 +118,7 @@ fn test_consume_tail() {
         TOPIC.to_string(),
         Some(0),
         &kafka_config,
-        Some(-1),
+        Some(StartPosition::Offset(-1)),
     )
     .expect("failed to initialise kafka source");
 
@@ -120,3 +132,705 @@ fn test_consume_tail() {
     assert_eq!(got.key(), tail.key());
     assert_eq!(got.payload(), tail.payload());
 }
+
+#[test]
+fn test_consume_from_timestamp() {
+    let addr = maybe_skip_integration!();
+
+    static TOPIC: &str = "timestamp-topic";
+
+    let kafka_config = KafkaOpts {
+        timeout: Duration::from_secs(5),
+        group: "bananas".to_string(),
+        isolation: IsolationLevel::ReadCommitted,
+        additional_args: vec![],
+    };
+
+    let msg = Message::new(
+        TOPIC,
+        0,
+        0,
+        None,
+        None,
+        Some("banana-key".into()),
+        Some("platanos".into()),
+    );
+
+    let tail = Message::new(
+        TOPIC,
+        0,
+        0,
+        None,
+        None,
+        Some("banana-key".into()),
+        Some("tail message".into()),
+    );
+
+    let mut sink = ktool::sink::kafka::Kafka::new(
+        vec![addr.clone()],
+        TOPIC.to_string(),
+        Some(0),
+        &kafka_config,
+        &ProduceOpts::default(),
+    )
+    .expect("failed to initialise kafka sink");
+
+    sink.write(&msg).expect("publishing message failed");
+    sink.write(&msg).expect("publishing message failed");
+    sink.flush().expect("failed to flush producer");
+
+    std::thread::sleep(Duration::from_millis(100));
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64;
+    std::thread::sleep(Duration::from_millis(100));
+
+    sink.write(&tail).expect("publishing message failed");
+    sink.flush().expect("failed to flush producer");
+
+    let mut source = ktool::source::kafka::new(
+        vec![addr],
+        TOPIC.to_string(),
+        Some(0),
+        &kafka_config,
+        Some(StartPosition::Timestamp(now)),
+    )
+    .expect("failed to initialise kafka source");
+
+    let got = source
+        .next()
+        .expect("no message received")
+        .expect("unexpected consume error");
+
+    assert_eq!(got.key(), tail.key());
+    assert_eq!(got.payload(), tail.payload());
+}
+
+#[test]
+fn test_consume_from_group() {
+    let addr = maybe_skip_integration!();
+
+    static TOPIC: &str = "group-topic";
+    static GROUP: &str = "stuck-consumer";
+
+    let kafka_config = KafkaOpts {
+        timeout: Duration::from_secs(5),
+        group: "bananas".to_string(),
+        isolation: IsolationLevel::ReadCommitted,
+        additional_args: vec![],
+    };
+
+    let msg = Message::new(
+        TOPIC,
+        0,
+        0,
+        None,
+        None,
+        Some("banana-key".into()),
+        Some("platanos".into()),
+    );
+
+    let next = Message::new(
+        TOPIC,
+        0,
+        0,
+        None,
+        None,
+        Some("banana-key".into()),
+        Some("next message".into()),
+    );
+
+    let mut sink = ktool::sink::kafka::Kafka::new(
+        vec![addr.clone()],
+        TOPIC.to_string(),
+        Some(0),
+        &kafka_config,
+        &ProduceOpts::default(),
+    )
+    .expect("failed to initialise kafka sink");
+
+    sink.write(&msg).expect("publishing message failed");
+    sink.write(&next).expect("publishing message failed");
+    sink.flush().expect("failed to flush producer");
+
+    // Commit the first message on behalf of the other group.
+    let group_config = KafkaOpts {
+        timeout: Duration::from_secs(5),
+        group: GROUP.to_string(),
+        isolation: IsolationLevel::ReadCommitted,
+        additional_args: vec![],
+    };
+    ktool::source::kafka::commit_offsets(
+        vec![addr.clone()],
+        &group_config,
+        [((TOPIC.to_string(), 0), 1)],
+    )
+    .expect("failed to commit offsets");
+
+    let mut source = ktool::source::kafka::new(
+        vec![addr],
+        TOPIC.to_string(),
+        Some(0),
+        &kafka_config,
+        Some(StartPosition::Committed {
+            group: GROUP.to_string(),
+            brokers: None,
+        }),
+    )
+    .expect("failed to initialise kafka source");
+
+    let got = source
+        .next()
+        .expect("no message received")
+        .expect("unexpected consume error");
+
+    assert_eq!(got.offset(), 1);
+    assert_eq!(got.payload(), next.payload());
+}
+
+#[test]
+fn test_produce_preserves_timestamp() {
+    let addr = maybe_skip_integration!();
+
+    static TOPIC: &str = "timestamp-preserve-topic";
+
+    let kafka_config = KafkaOpts {
+        timeout: Duration::from_secs(5),
+        group: "bananas".to_string(),
+        isolation: IsolationLevel::ReadCommitted,
+        additional_args: vec![],
+    };
+
+    // An hour ago, well within the default topic retention.
+    let ts = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64
+        - 60 * 60 * 1000;
+
+    let msg = Message::new(
+        TOPIC,
+        0,
+        0,
+        Some(Timestamp::CreateTime(ts)),
+        None,
+        Some("banana-key".into()),
+        Some("platanos".into()),
+    );
+
+    let mut sink = ktool::sink::kafka::Kafka::new(
+        vec![addr.clone()],
+        TOPIC.to_string(),
+        Some(0),
+        &kafka_config,
+        &ProduceOpts::default(),
+    )
+    .expect("failed to initialise kafka sink");
+
+    sink.write(&msg).expect("publishing message failed");
+    sink.flush().expect("failed to flush producer");
+
+    let mut source = ktool::source::kafka::new(
+        vec![addr],
+        TOPIC.to_string(),
+        Some(0),
+        &kafka_config,
+        Some(StartPosition::Offset(-1)),
+    )
+    .expect("failed to initialise kafka source");
+
+    let got = source
+        .next()
+        .expect("no message received")
+        .expect("unexpected consume error");
+
+    assert_eq!(got.timestamp(), Some(&Timestamp::CreateTime(ts)));
+    assert_eq!(got.payload(), msg.payload());
+}
+
+#[test]
+fn test_produce_delivery_offsets() {
+    let addr = maybe_skip_integration!();
+
+    static TOPIC: &str = "delivery-offsets-topic";
+
+    let kafka_config = KafkaOpts {
+        timeout: Duration::from_secs(5),
+        group: "bananas".to_string(),
+        isolation: IsolationLevel::ReadCommitted,
+        additional_args: vec![],
+    };
+
+    let mut sink = ktool::sink::kafka::Kafka::new(
+        vec![addr],
+        TOPIC.to_string(),
+        Some(0),
+        &kafka_config,
+        &ProduceOpts::default(),
+    )
+    .expect("failed to initialise kafka sink");
+
+    let msg = Message::new(TOPIC, 0, 0, None, None, None, Some("platanos".into()));
+    for _ in 0..3 {
+        sink.write(&msg).expect("publishing message failed");
+    }
+    sink.flush().expect("failed to flush producer");
+
+    let report = sink
+        .delivery_report()
+        .expect("kafka sink reports deliveries");
+    assert_eq!(report.delivered, 3);
+
+    let offsets = &report.offsets[&(TOPIC.to_string(), 0)];
+    assert_eq!(offsets.end() - offsets.start(), 2);
+}
+
+#[test]
+fn test_partition_offsets() {
+    let addr = maybe_skip_integration!();
+
+    static TOPIC: &str = "partition-offsets-topic";
+
+    let kafka_config = KafkaOpts {
+        timeout: Duration::from_secs(5),
+        group: "bananas".to_string(),
+        isolation: IsolationLevel::ReadCommitted,
+        additional_args: vec![],
+    };
+
+    let mut sink = ktool::sink::kafka::Kafka::new(
+        vec![addr.clone()],
+        TOPIC.to_string(),
+        Some(0),
+        &kafka_config,
+        &ProduceOpts::default(),
+    )
+    .expect("failed to initialise kafka sink");
+
+    // An hour ago, well within the default topic retention.
+    let base = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64
+        - 60 * 60 * 1000;
+
+    for ts in [base, base + 1000, base + 2000] {
+        let msg = Message::new(
+            TOPIC,
+            0,
+            0,
+            Some(Timestamp::CreateTime(ts)),
+            None,
+            None,
+            Some("platanos".into()),
+        );
+        sink.write(&msg).expect("publishing message failed");
+    }
+    sink.flush().expect("failed to flush producer");
+
+    let partitions = [(TOPIC.to_string(), 0)];
+    let got =
+        ktool::source::kafka::partition_offsets(vec![addr.clone()], &partitions, &kafka_config)
+            .expect("failed to read partition offsets");
+
+    assert_eq!(got.len(), 1);
+    assert!(got[0].count() >= 3);
+    assert_eq!(got[0].last_timestamp, Some(base + 2000));
+
+    let at =
+        ktool::source::kafka::offsets_for_time(vec![addr], &partitions, base + 1000, &kafka_config)
+            .expect("failed to resolve offsets for time");
+    assert_eq!(at[&partitions[0]], Some(got[0].high - 2));
+}
+
+#[test]
+fn test_describe_group() {
+    let addr = maybe_skip_integration!();
+
+    static TOPIC: &str = "describe-group-topic";
+
+    let kafka_config = KafkaOpts {
+        timeout: Duration::from_secs(5),
+        group: "describe-group".to_string(),
+        isolation: IsolationLevel::ReadCommitted,
+        additional_args: vec![],
+    };
+
+    let mut sink = ktool::sink::kafka::Kafka::new(
+        vec![addr.clone()],
+        TOPIC.to_string(),
+        Some(0),
+        &kafka_config,
+        &ProduceOpts::default(),
+    )
+    .expect("failed to initialise kafka sink");
+
+    for _ in 0..3 {
+        let msg = Message::new(TOPIC, 0, 0, None, None, None, Some("platanos".into()));
+        sink.write(&msg).expect("publishing message failed");
+    }
+    sink.flush().expect("failed to flush producer");
+
+    ktool::source::kafka::commit_offsets(
+        vec![addr.clone()],
+        &kafka_config,
+        [((TOPIC.to_string(), 0), 1)],
+    )
+    .expect("failed to commit offsets");
+
+    let group = ktool::consumer_group::describe(vec![addr], "describe-group", &kafka_config)
+        .expect("failed to describe group");
+
+    assert!(group.members.is_empty());
+    let p = group
+        .offsets
+        .iter()
+        .find(|p| p.topic == TOPIC && p.partition == 0)
+        .expect("committed partition not described");
+    assert_eq!(p.committed, Some(1));
+    assert_eq!(p.lag, Some(p.high - 1));
+}
+
+#[test]
+fn test_reset_group() {
+    let addr = maybe_skip_integration!();
+
+    static TOPIC: &str = "reset-group-topic";
+    static GROUP: &str = "reset-group";
+
+    let kafka_config = KafkaOpts {
+        timeout: Duration::from_secs(5),
+        group: "bananas".to_string(),
+        isolation: IsolationLevel::ReadCommitted,
+        additional_args: vec![],
+    };
+
+    let mut sink = ktool::sink::kafka::Kafka::new(
+        vec![addr.clone()],
+        TOPIC.to_string(),
+        Some(0),
+        &kafka_config,
+        &ProduceOpts::default(),
+    )
+    .expect("failed to initialise kafka sink");
+
+    for _ in 0..5 {
+        let msg = Message::new(TOPIC, 0, 0, None, None, None, Some("platanos".into()));
+        sink.write(&msg).expect("publishing message failed");
+    }
+    sink.flush().expect("failed to flush producer");
+
+    ktool::source::kafka::commit_group_offsets(
+        vec![addr.clone()],
+        GROUP,
+        &kafka_config,
+        [((TOPIC.to_string(), 0), 2)],
+    )
+    .expect("failed to commit offsets");
+
+    assert_eq!(
+        ktool::consumer_group::active_members(vec![addr.clone()], GROUP, &kafka_config)
+            .expect("failed to read group members"),
+        0
+    );
+
+    let selection = [TOPIC.parse().unwrap()];
+    let changes = ktool::consumer_group::plan_reset(
+        vec![addr.clone()],
+        GROUP,
+        &selection,
+        ResetStrategy::ShiftBy(1),
+        &kafka_config,
+    )
+    .expect("failed to plan reset");
+
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].current, Some(2));
+    assert_eq!(changes[0].new, 3);
+
+    ktool::consumer_group::apply_reset(vec![addr.clone()], GROUP, &changes, &kafka_config)
+        .expect("failed to apply reset");
+
+    let committed = ktool::source::kafka::committed_offsets(
+        &[addr],
+        GROUP,
+        &[(TOPIC.to_string(), 0)],
+        &kafka_config,
+    )
+    .expect("failed to read committed offsets");
+    assert_eq!(committed[&(TOPIC.to_string(), 0)], Some(3));
+}
+
+#[test]
+fn test_export_import_group() {
+    let addr = maybe_skip_integration!();
+
+    static TOPIC: &str = "export-group-topic";
+    static GROUP: &str = "export-group";
+
+    let kafka_config = KafkaOpts {
+        timeout: Duration::from_secs(5),
+        group: "bananas".to_string(),
+        isolation: IsolationLevel::ReadCommitted,
+        additional_args: vec![],
+    };
+
+    let mut sink = ktool::sink::kafka::Kafka::new(
+        vec![addr.clone()],
+        TOPIC.to_string(),
+        Some(0),
+        &kafka_config,
+        &ProduceOpts::default(),
+    )
+    .expect("failed to initialise kafka sink");
+
+    // An hour ago, well within the default topic retention.
+    let base = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64
+        - 60 * 60 * 1000;
+
+    for ts in [base, base + 1000, base + 2000] {
+        let msg = Message::new(
+            TOPIC,
+            0,
+            0,
+            Some(Timestamp::CreateTime(ts)),
+            None,
+            None,
+            Some("platanos".into()),
+        );
+        sink.write(&msg).expect("publishing message failed");
+    }
+    sink.flush().expect("failed to flush producer");
+
+    let partitions = [(TOPIC.to_string(), 0)];
+    let high =
+        ktool::source::kafka::partition_offsets(vec![addr.clone()], &partitions, &kafka_config)
+            .expect("failed to read partition offsets")[0]
+            .high;
+
+    // Position the group at the second message written above.
+    ktool::source::kafka::commit_group_offsets(
+        vec![addr.clone()],
+        GROUP,
+        &kafka_config,
+        [(partitions[0].clone(), high - 2)],
+    )
+    .expect("failed to commit offsets");
+
+    let snapshot =
+        ktool::consumer_group::export(vec![addr.clone()], &[GROUP.to_string()], &kafka_config)
+            .expect("failed to export offsets");
+
+    assert_eq!(snapshot.groups.len(), 1);
+    let offsets = &snapshot.groups[0].offsets;
+    assert_eq!(offsets.len(), 1);
+    assert_eq!(offsets[0].offset, high - 2);
+    assert!(!offsets[0].at_end);
+    assert_eq!(offsets[0].timestamp, Some(base + 1000));
+
+    // Move the group away, then restore it by timestamp.
+    ktool::source::kafka::commit_group_offsets(
+        vec![addr.clone()],
+        GROUP,
+        &kafka_config,
+        [(partitions[0].clone(), high)],
+    )
+    .expect("failed to commit offsets");
+
+    let changes = ktool::consumer_group::plan_import(
+        vec![addr.clone()],
+        &snapshot.groups[0],
+        true,
+        &kafka_config,
+    )
+    .expect("failed to plan import");
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].current, Some(high));
+    assert_eq!(changes[0].new, high - 2);
+
+    ktool::consumer_group::apply_reset(vec![addr.clone()], GROUP, &changes, &kafka_config)
+        .expect("failed to apply import");
+
+    let committed =
+        ktool::source::kafka::committed_offsets(&[addr], GROUP, &partitions, &kafka_config)
+            .expect("failed to read committed offsets");
+    assert_eq!(committed[&partitions[0]], Some(high - 2));
+}
+
+#[test]
+fn test_cp_exactly_once() {
+    let addr = maybe_skip_integration!();
+
+    static SOURCE: &str = "exactly-once-source";
+    static DEST: &str = "exactly-once-dest";
+    static GROUP: &str = "exactly-once-group";
+
+    let kafka_config = KafkaOpts {
+        timeout: Duration::from_secs(5),
+        group: "bananas".to_string(),
+        isolation: IsolationLevel::ReadCommitted,
+        additional_args: vec![],
+    };
+
+    let mut sink = ktool::sink::kafka::Kafka::new(
+        vec![addr.clone()],
+        SOURCE.to_string(),
+        Some(0),
+        &kafka_config,
+        &ProduceOpts::default(),
+    )
+    .expect("failed to initialise kafka sink");
+
+    let payloads = (0..5)
+        .map(|i| format!("platanos-{}", i))
+        .collect::<Vec<_>>();
+    for payload in &payloads {
+        let msg = Message::new(SOURCE, 0, 0, None, None, None, Some(payload.clone().into()));
+        sink.write(&msg).expect("publishing message failed");
+    }
+    sink.flush().expect("failed to flush producer");
+
+    // Copy the messages in transactions of 2, committing the source offsets
+    // alongside each.
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.arg("cp")
+        .arg("--exactly-once")
+        .arg("--transaction-size=2")
+        .arg(format!("--group={}", GROUP))
+        .arg("--timeout=5")
+        .arg(format!("kafka://{}/{}/0", addr, SOURCE))
+        .arg(format!("kafka://{}/{}/0", addr, DEST));
+
+    let output = cmd.unwrap();
+    assert!(output.status.success());
+
+    // Every copied message is visible to a read_committed consumer.
+    let got = ktool::source::kafka::new(
+        vec![addr.clone()],
+        DEST.to_string(),
+        Some(0),
+        &kafka_config,
+        None,
+    )
+    .expect("failed to initialise kafka source")
+    .map(|v| v.expect("unexpected consume error"))
+    .collect::<Vec<_>>();
+
+    assert!(got.len() >= payloads.len());
+    let got = &got[got.len() - payloads.len()..];
+    for (msg, want) in got.iter().zip(&payloads) {
+        assert_eq!(msg.payload(), Some(want.as_bytes()));
+    }
+
+    // Each transaction of 2 messages is followed by a commit marker.
+    let steps = got
+        .windows(2)
+        .map(|w| w[1].offset() - w[0].offset())
+        .collect::<Vec<_>>();
+    assert_eq!(steps, [1, 2, 1, 2]);
+
+    // The group's committed offset on the destination cluster is the end of
+    // the source partition.
+    let partitions = [(SOURCE.to_string(), 0)];
+    let high =
+        ktool::source::kafka::partition_offsets(vec![addr.clone()], &partitions, &kafka_config)
+            .expect("failed to read partition offsets")[0]
+            .high;
+
+    let committed =
+        ktool::source::kafka::committed_offsets(&[addr], GROUP, &partitions, &kafka_config)
+            .expect("failed to read committed offsets");
+    assert_eq!(committed[&partitions[0]], Some(high));
+}
+
+#[test]
+fn test_skip_inspector() {
+    use rdkafka::{
+        config::ClientConfig,
+        producer::{BaseRecord, Producer, ThreadedProducer},
+    };
+
+    let addr = maybe_skip_integration!();
+
+    static TOPIC: &str = "skip-inspector-topic";
+
+    let kafka_config = KafkaOpts {
+        timeout: Duration::from_secs(5),
+        group: "bananas".to_string(),
+        isolation: IsolationLevel::ReadCommitted,
+        additional_args: vec![],
+    };
+
+    // Ensure the topic exists, and find where the transactions below begin.
+    let mut sink = ktool::sink::kafka::Kafka::new(
+        vec![addr.clone()],
+        TOPIC.to_string(),
+        Some(0),
+        &kafka_config,
+        &ProduceOpts::default(),
+    )
+    .expect("failed to initialise kafka sink");
+    let msg = Message::new(TOPIC, 0, 0, None, None, None, Some("platanos".into()));
+    sink.write(&msg).expect("publishing message failed");
+    sink.flush().expect("failed to flush producer");
+
+    let partitions = [(TOPIC.to_string(), 0)];
+    let base =
+        ktool::source::kafka::partition_offsets(vec![addr.clone()], &partitions, &kafka_config)
+            .expect("failed to read partition offsets")[0]
+            .high;
+
+    // Write an aborted transaction of 2 messages, then a committed
+    // transaction of 1.
+    let producer: ThreadedProducer<_> = ClientConfig::new()
+        .set("bootstrap.servers", &addr)
+        .set("transactional.id", "skip-inspector")
+        .create()
+        .expect("failed to initialise transactional producer");
+    producer
+        .init_transactions(kafka_config.timeout)
+        .expect("failed to initialise transactions");
+
+    for commit in [false, true] {
+        producer.begin_transaction().unwrap();
+        for _ in 0..(if commit { 1 } else { 2 }) {
+            producer
+                .send(
+                    BaseRecord::<(), _>::to(TOPIC)
+                        .partition(0)
+                        .payload("platanos"),
+                )
+                .map_err(|(e, _)| e)
+                .expect("failed to enqueue message");
+        }
+        if commit {
+            producer.commit_transaction(kafka_config.timeout).unwrap();
+        } else {
+            producer.flush(kafka_config.timeout);
+            producer.abort_transaction(kafka_config.timeout).unwrap();
+        }
+    }
+
+    let mut inspector = ktool::source::kafka::SkipInspector::new(vec![addr], &kafka_config)
+        .expect("failed to initialise inspector");
+
+    assert_eq!(
+        inspector
+            .inspect(TOPIC, 0, base..=base + 2)
+            .expect("failed to inspect skipped offsets"),
+        [
+            (base..=base + 1, SkipReason::Aborted),
+            (base + 2..=base + 2, SkipReason::Marker),
+        ]
+    );
+    assert_eq!(
+        inspector
+            .inspect(TOPIC, 0, base + 4..=base + 4)
+            .expect("failed to inspect skipped offsets"),
+        [(base + 4..=base + 4, SkipReason::Marker)]
+    );
+}